@@ -0,0 +1,144 @@
+//! The acid chunk, which loop libraries use for tempo, meter and root note.
+
+use binrw::{binrw, BinReaderExt};
+use core::ops::BitOr;
+
+/// Loop metadata written by ACID and the many tools that followed it.
+#[binrw]
+#[brw(magic = b"acid")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcidChunk {
+    #[br(little)]
+    pub size: u32,
+    pub flags: AcidFlags,
+    /// The MIDI note of the root, if [`AcidFlags::ROOT_NOTE_SET`] is set.
+    #[br(little)]
+    pub root_note: u16,
+    #[br(little)]
+    pub reserved1: u16,
+    #[br(little)]
+    pub reserved2: f32,
+    /// The length of the loop in beats.
+    #[br(little)]
+    pub num_beats: u32,
+    #[br(little)]
+    pub meter_denominator: u16,
+    #[br(little)]
+    pub meter_numerator: u16,
+    /// Beats per minute.
+    #[br(little)]
+    pub tempo: f32,
+}
+
+impl AcidChunk {
+    /// Parses an acid chunk, including its id and size. Returns `None` unless the body is 24
+    /// bytes long.
+    pub(crate) fn parse(chunk: &[u8]) -> Option<AcidChunk> {
+        if chunk.len() != 8 + 24 {
+            return None;
+        }
+        crate::parse_whole(chunk, |cursor| cursor.read_le())
+    }
+
+    /// A loop of `num_beats` beats at `tempo` beats per minute, in 4/4.
+    pub fn new(tempo: f32, num_beats: u32) -> AcidChunk {
+        AcidChunk {
+            size: 24,
+            flags: AcidFlags::STRETCH,
+            root_note: 60,
+            reserved1: 0x8000,
+            reserved2: 0.0,
+            num_beats,
+            meter_denominator: 4,
+            meter_numerator: 4,
+            tempo,
+        }
+    }
+
+    /// The MIDI note of the root, if it is set.
+    pub fn root(&self) -> Option<u16> {
+        if self.flags.contains(AcidFlags::ROOT_NOTE_SET) {
+            Some(self.root_note)
+        } else {
+            None
+        }
+    }
+
+    /// Sets the root note, along with [`AcidFlags::ROOT_NOTE_SET`].
+    pub fn set_root(&mut self, note: u16) {
+        self.root_note = note;
+        self.flags.insert(AcidFlags::ROOT_NOTE_SET);
+    }
+}
+
+/// The flag bits of an [`AcidChunk`]. Bits without a constant are kept as is.
+#[binrw]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AcidFlags {
+    #[br(little)]
+    bits: u32,
+}
+
+impl AcidFlags {
+    /// The audio is played once, rather than looped to the tempo.
+    pub const ONE_SHOT: AcidFlags = AcidFlags::from_bits(0x1);
+    pub const ROOT_NOTE_SET: AcidFlags = AcidFlags::from_bits(0x2);
+    /// The audio is stretched to the tempo of the project.
+    pub const STRETCH: AcidFlags = AcidFlags::from_bits(0x4);
+    /// The audio is streamed from disk rather than loaded into memory.
+    pub const DISK_BASED: AcidFlags = AcidFlags::from_bits(0x8);
+
+    pub const fn empty() -> AcidFlags {
+        AcidFlags { bits: 0 }
+    }
+
+    pub const fn from_bits(bits: u32) -> AcidFlags {
+        AcidFlags { bits }
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.bits
+    }
+
+    /// Whether every bit of `flags` is set.
+    pub fn contains(self, flags: AcidFlags) -> bool {
+        self.bits & flags.bits == flags.bits
+    }
+
+    pub fn insert(&mut self, flags: AcidFlags) {
+        self.bits |= flags.bits;
+    }
+
+    pub fn remove(&mut self, flags: AcidFlags) {
+        self.bits &= !flags.bits;
+    }
+}
+
+impl BitOr for AcidFlags {
+    type Output = AcidFlags;
+
+    fn bitor(self, rhs: AcidFlags) -> AcidFlags {
+        AcidFlags::from_bits(self.bits | rhs.bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_sets_flags() {
+        let mut acid = AcidChunk::new(120.0, 8);
+        assert_eq!(acid.root(), None);
+        acid.set_root(57);
+        assert_eq!(acid.root(), Some(57));
+        acid.flags
+            .insert(AcidFlags::ONE_SHOT | AcidFlags::DISK_BASED);
+        acid.flags.remove(AcidFlags::STRETCH);
+        assert_eq!(acid.flags.bits(), 0xB);
+        assert!(acid
+            .flags
+            .contains(AcidFlags::ONE_SHOT | AcidFlags::ROOT_NOTE_SET));
+        assert!(!acid.flags.contains(AcidFlags::STRETCH));
+    }
+}
@@ -0,0 +1,717 @@
+//! Block based ADPCM codecs, which compress 16-bit samples to 4 bits each.
+//!
+//! Every block starts with a header per channel holding the state of the decoder, so blocks can
+//! be decoded independently. The last block of a data chunk may be shorter than `block_align`.
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+use crate::{io, FormatChunk, Result, WaveFormat, WaverlyError};
+
+/// Step sizes of IMA ADPCM, indexed by the step index.
+const IMA_STEPS: [i32; 89] = [
+    7, 8, 9, 10, 11, 12, 13, 14, 16, 17, 19, 21, 23, 25, 28, 31, 34, 37, 41, 45, 50, 55, 60, 66,
+    73, 80, 88, 97, 107, 118, 130, 143, 157, 173, 190, 209, 230, 253, 279, 307, 337, 371, 408, 449,
+    494, 544, 598, 658, 724, 796, 876, 963, 1060, 1166, 1282, 1411, 1552, 1707, 1878, 2066, 2272,
+    2499, 2749, 3024, 3327, 3660, 4026, 4428, 4871, 5358, 5894, 6484, 7132, 7845, 8630, 9493,
+    10442, 11487, 12635, 13899, 15289, 16818, 18500, 20350, 22385, 24623, 27086, 29794, 32767,
+];
+
+/// Change of the step index after each nibble, indexed by the nibble without its sign bit.
+const IMA_INDEX_CHANGES: [i32; 8] = [-1, -1, -1, -1, 2, 4, 6, 8];
+
+/// The state of a single IMA ADPCM channel.
+#[derive(Debug, Clone, Copy, Default)]
+struct ImaChannel {
+    predictor: i32,
+    step_index: i32,
+}
+
+impl ImaChannel {
+    fn decode(&mut self, nibble: u8) -> i16 {
+        let step = IMA_STEPS[self.step_index as usize];
+        let mut diff = step >> 3;
+        if nibble & 1 != 0 {
+            diff += step >> 2;
+        }
+        if nibble & 2 != 0 {
+            diff += step >> 1;
+        }
+        if nibble & 4 != 0 {
+            diff += step;
+        }
+        if nibble & 8 != 0 {
+            diff = -diff;
+        }
+        self.predictor = (self.predictor + diff).clamp(i16::MIN as i32, i16::MAX as i32);
+        self.step_index = (self.step_index + IMA_INDEX_CHANGES[(nibble & 7) as usize]).clamp(0, 88);
+        self.predictor as i16
+    }
+
+    fn encode(&mut self, sample: i16) -> u8 {
+        let mut delta = sample as i32 - self.predictor;
+        let mut nibble = 0;
+        if delta < 0 {
+            nibble = 8;
+            delta = -delta;
+        }
+        let mut step = IMA_STEPS[self.step_index as usize];
+        for bit in [4, 2, 1] {
+            if delta >= step {
+                nibble |= bit;
+                delta -= step;
+            }
+            step >>= 1;
+        }
+        // Track the decoder so that rounding errors do not accumulate.
+        self.decode(nibble);
+        nibble
+    }
+}
+
+/// The block layout of an IMA ADPCM format.
+#[derive(Debug, Clone, Copy)]
+struct ImaBlocks {
+    num_channels: usize,
+    block_align: usize,
+    samples_per_block: usize,
+}
+
+impl ImaBlocks {
+    fn from_format(format: &FormatChunk) -> Result<ImaBlocks> {
+        let num_channels = format.num_channels as usize;
+        let block_align = format.block_align as usize;
+        if format.bits_per_sample != 4 {
+            return Err(WaverlyError::UnsupportedFormat {
+                format: format.audio_format,
+                bits_per_sample: format.bits_per_sample,
+            });
+        }
+        let header = 4 * num_channels;
+        if num_channels == 0
+            || block_align <= header
+            || !(block_align - header).is_multiple_of(header)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "IMA ADPCM block alignment does not hold a whole number of sample words.",
+            )
+            .into());
+        }
+        // The samples per block field of the format extension may leave the end of each block
+        // unused, but cannot exceed what fits.
+        let max_samples_per_block = (block_align - header) * 2 / num_channels + 1;
+        let samples_per_block = match format.extra.get(..2) {
+            Some(&[low, high]) if u16::from_le_bytes([low, high]) != 0 => {
+                u16::from_le_bytes([low, high]) as usize
+            }
+            _ => max_samples_per_block,
+        };
+        if samples_per_block > max_samples_per_block {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "IMA ADPCM samples per block do not fit the block alignment.",
+            )
+            .into());
+        }
+        Ok(ImaBlocks {
+            num_channels,
+            block_align,
+            samples_per_block,
+        })
+    }
+
+    /// The number of sample frames of a block of `len` bytes.
+    fn frames(&self, len: usize) -> usize {
+        let header = 4 * self.num_channels;
+        let frames = (len.saturating_sub(header) / header) * 8 + 1;
+        frames.min(self.samples_per_block)
+    }
+}
+
+/// Sets the block alignment, byte rate and format extension of an ADPCM format, using the block
+/// size encoders commonly use for the sample rate. Fails if there are no channels, or too many for
+/// the block size to fit the format chunk.
+pub(crate) fn set_block_layout(format: &mut FormatChunk) -> Result<()> {
+    let num_channels = format.num_channels as u32;
+    let block_align = 256 * num_channels * (format.sample_rate / 11025).clamp(1, 4);
+    // The size of the block header of each channel, and the samples it holds.
+    let (header, header_samples) = match format.audio_format {
+        WaveFormat::MsAdpcm => (7, 2),
+        _ => (4, 1),
+    };
+    let samples_per_block = (block_align - header * num_channels)
+        .checked_div(num_channels)
+        .map(|samples| samples * 2 + header_samples);
+    let (block_align, samples_per_block) = match (
+        u16::try_from(block_align),
+        samples_per_block.and_then(|samples| u16::try_from(samples).ok()),
+    ) {
+        (Ok(block_align), Some(samples_per_block)) => (block_align, samples_per_block),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "ADPCM block size does not fit the format chunk.",
+            )
+            .into())
+        }
+    };
+    format.extra = match format.audio_format {
+        WaveFormat::MsAdpcm => MsAdpcmFormat::standard(samples_per_block).to_bytes(),
+        _ => samples_per_block.to_le_bytes().to_vec(),
+    };
+    format.extension_size = Some(format.extra.len() as u16);
+    format.size = 18 + format.extra.len() as u32;
+    format.block_align = block_align;
+    format.byte_rate =
+        (format.sample_rate as u64 * block_align as u64 / samples_per_block as u64) as u32;
+    Ok(())
+}
+
+/// Decodes IMA ADPCM blocks to interleaved 16-bit samples.
+pub(crate) fn decode_ima(format: &FormatChunk, data: &[u8]) -> Result<Vec<i16>> {
+    let blocks = ImaBlocks::from_format(format)?;
+    let num_channels = blocks.num_channels;
+    let mut samples =
+        Vec::with_capacity(data.len() / blocks.block_align * blocks.samples_per_block);
+    let mut channels = vec![ImaChannel::default(); num_channels];
+    for block in data.chunks(blocks.block_align) {
+        if block.len() < 4 * num_channels {
+            break;
+        }
+        let start = samples.len();
+        let frames = blocks.frames(block.len());
+        samples.resize(start + frames * num_channels, 0);
+
+        let (header, body) = block.split_at(4 * num_channels);
+        for (channel, state) in channels.iter_mut().enumerate() {
+            let header = &header[4 * channel..];
+            state.predictor = i16::from_le_bytes([header[0], header[1]]) as i32;
+            state.step_index = (header[2] as i32).min(88);
+            samples[start + channel] = state.predictor as i16;
+        }
+        // Each channel in turn stores 8 samples in 4 bytes, low nibble first.
+        for (word, bytes) in body.chunks_exact(4).enumerate() {
+            let channel = word % num_channels;
+            let first_frame = 1 + word / num_channels * 8;
+            for (index, byte) in bytes.iter().enumerate() {
+                for (offset, nibble) in [(0, byte & 0x0F), (1, byte >> 4)] {
+                    let frame = first_frame + 2 * index + offset;
+                    if frame < frames {
+                        samples[start + frame * num_channels + channel] =
+                            channels[channel].decode(nibble);
+                    }
+                }
+            }
+        }
+    }
+    Ok(samples)
+}
+
+/// Encodes interleaved 16-bit samples to IMA ADPCM blocks. The last block only holds as many
+/// sample words as needed, padded with silence.
+pub(crate) fn encode_ima(format: &FormatChunk, samples: &[i16], out: &mut Vec<u8>) -> Result<()> {
+    let blocks = ImaBlocks::from_format(format)?;
+    let num_channels = blocks.num_channels;
+    let mut channels = vec![ImaChannel::default(); num_channels];
+    for block in samples.chunks(blocks.samples_per_block * num_channels) {
+        let frames = block.len() / num_channels;
+        for (channel, state) in channels.iter_mut().enumerate() {
+            state.predictor = block[channel] as i32;
+            out.extend_from_slice(&block[channel].to_le_bytes());
+            out.extend_from_slice(&[state.step_index as u8, 0]);
+        }
+        let words = (frames - 1).div_ceil(8);
+        for word in 0..words {
+            for (channel, state) in channels.iter_mut().enumerate() {
+                let mut bytes = [0; 4];
+                for (nibble, frame) in (1 + word * 8..1 + (word + 1) * 8).enumerate() {
+                    let sample = if frame < frames {
+                        block[frame * num_channels + channel]
+                    } else {
+                        0
+                    };
+                    bytes[nibble / 2] |= state.encode(sample) << (4 * (nibble % 2));
+                }
+                out.extend_from_slice(&bytes);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The number of sample frames of each full block of an ADPCM format.
+pub(crate) fn samples_per_block(format: &FormatChunk) -> Result<usize> {
+    match format.audio_format {
+        WaveFormat::MsAdpcm => Ok(MsBlocks::from_format(format)?.format.samples_per_block as usize),
+        _ => Ok(ImaBlocks::from_format(format)?.samples_per_block),
+    }
+}
+
+/// The largest MS ADPCM delta, as in ffmpeg, so that adapting it cannot overflow.
+const MS_MAX_DELTA: i32 = i32::MAX / 768;
+
+/// Change of the MS ADPCM delta after each nibble, in 1/256ths, indexed by the unsigned nibble.
+const MS_ADAPTATION: [i32; 16] = [
+    230, 230, 230, 230, 307, 409, 512, 614, 768, 614, 512, 409, 307, 230, 230, 230,
+];
+
+/// A pair of predictor coefficients of MS ADPCM, in 1/256ths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MsAdpcmCoefficient {
+    /// Weight of the previous sample.
+    pub coef1: i16,
+    /// Weight of the sample before the previous one.
+    pub coef2: i16,
+}
+
+/// The format extension of [`WaveFormat::MsAdpcm`], following the extension size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MsAdpcmFormat {
+    pub samples_per_block: u16,
+    /// The predictors each block can choose from, per channel.
+    pub coefficients: Vec<MsAdpcmCoefficient>,
+}
+
+impl MsAdpcmFormat {
+    /// The seven coefficient pairs every MS ADPCM file is expected to start with.
+    pub const STANDARD_COEFFICIENTS: [MsAdpcmCoefficient; 7] = [
+        MsAdpcmCoefficient {
+            coef1: 256,
+            coef2: 0,
+        },
+        MsAdpcmCoefficient {
+            coef1: 512,
+            coef2: -256,
+        },
+        MsAdpcmCoefficient { coef1: 0, coef2: 0 },
+        MsAdpcmCoefficient {
+            coef1: 192,
+            coef2: 64,
+        },
+        MsAdpcmCoefficient {
+            coef1: 240,
+            coef2: 0,
+        },
+        MsAdpcmCoefficient {
+            coef1: 460,
+            coef2: -208,
+        },
+        MsAdpcmCoefficient {
+            coef1: 392,
+            coef2: -232,
+        },
+    ];
+
+    /// The standard coefficients, with blocks of `samples_per_block` sample frames.
+    pub fn standard(samples_per_block: u16) -> MsAdpcmFormat {
+        MsAdpcmFormat {
+            samples_per_block,
+            coefficients: MsAdpcmFormat::STANDARD_COEFFICIENTS.to_vec(),
+        }
+    }
+
+    /// Parses the extra bytes of a format chunk, or returns `None` if they are too short for
+    /// the number of coefficients they declare.
+    pub fn parse(extra: &[u8]) -> Option<MsAdpcmFormat> {
+        let samples_per_block = u16::from_le_bytes([*extra.first()?, *extra.get(1)?]);
+        let num_coefficients = u16::from_le_bytes([*extra.get(2)?, *extra.get(3)?]) as usize;
+        let coefficients = extra.get(4..4 + 4 * num_coefficients)?;
+        Some(MsAdpcmFormat {
+            samples_per_block,
+            coefficients: coefficients
+                .chunks_exact(4)
+                .map(|pair| MsAdpcmCoefficient {
+                    coef1: i16::from_le_bytes([pair[0], pair[1]]),
+                    coef2: i16::from_le_bytes([pair[2], pair[3]]),
+                })
+                .collect(),
+        })
+    }
+
+    /// The extra bytes of a format chunk holding this extension.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(4 + 4 * self.coefficients.len());
+        data.extend_from_slice(&self.samples_per_block.to_le_bytes());
+        data.extend_from_slice(&(self.coefficients.len() as u16).to_le_bytes());
+        for coefficient in &self.coefficients {
+            data.extend_from_slice(&coefficient.coef1.to_le_bytes());
+            data.extend_from_slice(&coefficient.coef2.to_le_bytes());
+        }
+        data
+    }
+}
+
+/// The state of a single MS ADPCM channel.
+#[derive(Debug, Clone, Copy)]
+struct MsChannel {
+    coefficient: MsAdpcmCoefficient,
+    delta: i32,
+    sample1: i32,
+    sample2: i32,
+}
+
+impl MsChannel {
+    fn predict(&self) -> i32 {
+        // Computed in 64 bits, as two products of 16-bit values can overflow 32 bits.
+        ((self.sample1 as i64 * self.coefficient.coef1 as i64
+            + self.sample2 as i64 * self.coefficient.coef2 as i64)
+            >> 8) as i32
+    }
+
+    fn decode(&mut self, nibble: u8) -> i16 {
+        // Sign extend the nibble.
+        let signed = ((nibble << 4) as i8 >> 4) as i32;
+        let sample = (self.predict() + signed * self.delta).clamp(i16::MIN as i32, i16::MAX as i32);
+        self.sample2 = self.sample1;
+        self.sample1 = sample;
+        self.delta = ((MS_ADAPTATION[nibble as usize] * self.delta) >> 8).clamp(16, MS_MAX_DELTA);
+        sample as i16
+    }
+
+    fn encode(&mut self, sample: i16) -> u8 {
+        let error = sample as i32 - self.predict();
+        let bias = if error < 0 {
+            -self.delta / 2
+        } else {
+            self.delta / 2
+        };
+        let nibble = ((error + bias) / self.delta).clamp(-8, 7) as u8 & 0x0F;
+        self.decode(nibble);
+        nibble
+    }
+}
+
+/// The block layout of an MS ADPCM format.
+#[derive(Debug, Clone)]
+struct MsBlocks {
+    num_channels: usize,
+    block_align: usize,
+    format: MsAdpcmFormat,
+}
+
+impl MsBlocks {
+    fn from_format(format: &FormatChunk) -> Result<MsBlocks> {
+        let num_channels = format.num_channels as usize;
+        let block_align = format.block_align as usize;
+        if format.bits_per_sample != 4 {
+            return Err(WaverlyError::UnsupportedFormat {
+                format: format.audio_format,
+                bits_per_sample: format.bits_per_sample,
+            });
+        }
+        let invalid = |message| -> WaverlyError {
+            io::Error::new(io::ErrorKind::InvalidInput, message).into()
+        };
+        let ms_format = MsAdpcmFormat::parse(&format.extra)
+            .ok_or_else(|| invalid("MS ADPCM format extension is missing or truncated."))?;
+        if num_channels == 0 || block_align < 7 * num_channels {
+            return Err(invalid(
+                "MS ADPCM block alignment is too small for the block header.",
+            ));
+        }
+        let max_samples_per_block = (block_align - 7 * num_channels) * 2 / num_channels + 2;
+        if ms_format.samples_per_block < 2
+            || ms_format.samples_per_block as usize > max_samples_per_block
+        {
+            return Err(invalid(
+                "MS ADPCM samples per block do not fit the block alignment.",
+            ));
+        }
+        Ok(MsBlocks {
+            num_channels,
+            block_align,
+            format: ms_format,
+        })
+    }
+
+    /// The number of sample frames of a block of `len` bytes.
+    fn frames(&self, len: usize) -> usize {
+        let frames = len.saturating_sub(7 * self.num_channels) * 2 / self.num_channels + 2;
+        frames.min(self.format.samples_per_block as usize)
+    }
+
+    fn coefficient(&self, index: u8) -> Result<MsAdpcmCoefficient> {
+        self.format
+            .coefficients
+            .get(index as usize)
+            .copied()
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "MS ADPCM block refers to a coefficient pair that does not exist.",
+                )
+                .into()
+            })
+    }
+}
+
+/// Decodes MS ADPCM blocks to interleaved 16-bit samples.
+pub(crate) fn decode_ms(format: &FormatChunk, data: &[u8]) -> Result<Vec<i16>> {
+    let blocks = MsBlocks::from_format(format)?;
+    let num_channels = blocks.num_channels;
+    let header = 7 * num_channels;
+    let mut samples = Vec::new();
+    for block in data.chunks(blocks.block_align) {
+        if block.len() < header {
+            break;
+        }
+        // The header holds the predictor indices, then the deltas, then the second and first
+        // samples of each channel.
+        let field = |index: usize, channel: usize| {
+            let offset = num_channels + 2 * (index * num_channels + channel);
+            i16::from_le_bytes([block[offset], block[offset + 1]]) as i32
+        };
+        let mut channels = Vec::with_capacity(num_channels);
+        for (channel, &index) in block[..num_channels].iter().enumerate() {
+            channels.push(MsChannel {
+                coefficient: blocks.coefficient(index)?,
+                delta: field(0, channel),
+                sample1: field(1, channel),
+                sample2: field(2, channel),
+            });
+        }
+        samples.extend(channels.iter().map(|channel| channel.sample2 as i16));
+        samples.extend(channels.iter().map(|channel| channel.sample1 as i16));
+
+        // Nibbles follow in interleave order, high nibble first.
+        let nibbles = block[header..]
+            .iter()
+            .flat_map(|byte| [byte >> 4, byte & 0x0F]);
+        let count = (blocks.frames(block.len()) - 2) * num_channels;
+        for (index, nibble) in nibbles.take(count).enumerate() {
+            samples.push(channels[index % num_channels].decode(nibble));
+        }
+    }
+    Ok(samples)
+}
+
+/// Encodes interleaved 16-bit samples to MS ADPCM blocks, choosing the coefficient pair with the
+/// smallest error for each block and channel. The last block only holds as many bytes as needed,
+/// padded with silence.
+pub(crate) fn encode_ms(format: &FormatChunk, samples: &[i16], out: &mut Vec<u8>) -> Result<()> {
+    let blocks = MsBlocks::from_format(format)?;
+    let num_channels = blocks.num_channels;
+    let samples_per_block = blocks.format.samples_per_block as usize;
+    let mut deltas = vec![16; num_channels];
+    for block in samples.chunks(samples_per_block * num_channels) {
+        let frames = (block.len() / num_channels).max(2);
+        let sample = |frame: usize, channel: usize| -> i16 {
+            block
+                .get(frame * num_channels + channel)
+                .copied()
+                .unwrap_or(0)
+        };
+
+        let mut channels = Vec::with_capacity(num_channels);
+        for (channel, delta) in deltas.iter().enumerate() {
+            let start = MsChannel {
+                coefficient: MsAdpcmFormat::STANDARD_COEFFICIENTS[0],
+                delta: *delta,
+                sample1: sample(1, channel) as i32,
+                sample2: sample(0, channel) as i32,
+            };
+            let mut best = (0, start, u64::MAX);
+            for (index, &coefficient) in blocks.format.coefficients.iter().enumerate() {
+                let mut state = MsChannel {
+                    coefficient,
+                    ..start
+                };
+                let mut error = 0;
+                for frame in 2..frames {
+                    let input = sample(frame, channel);
+                    state.encode(input);
+                    error += (input as i64 - state.sample1 as i64).pow(2) as u64;
+                }
+                if error < best.2 {
+                    best = (
+                        index,
+                        MsChannel {
+                            coefficient,
+                            ..start
+                        },
+                        error,
+                    );
+                }
+            }
+            out.push(best.0 as u8);
+            channels.push(best.1);
+        }
+        for channel in &channels {
+            out.extend_from_slice(&(channel.delta as i16).to_le_bytes());
+        }
+        for channel in &channels {
+            out.extend_from_slice(&(channel.sample1 as i16).to_le_bytes());
+        }
+        for channel in &channels {
+            out.extend_from_slice(&(channel.sample2 as i16).to_le_bytes());
+        }
+
+        let mut high = None;
+        for index in 2 * num_channels..frames * num_channels {
+            let channel = index % num_channels;
+            let nibble = channels[channel].encode(sample(index / num_channels, channel));
+            match high.take() {
+                Some(high) => out.push(high << 4 | nibble),
+                None => high = Some(nibble),
+            }
+        }
+        if let Some(high) = high {
+            out.push(high << 4);
+        }
+        for (delta, channel) in deltas.iter_mut().zip(&channels) {
+            *delta = channel.delta;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ima_format(num_channels: u16, block_align: u16) -> FormatChunk {
+        FormatChunk {
+            size: 20,
+            audio_format: WaveFormat::ImaAdpcm,
+            num_channels,
+            sample_rate: 8000,
+            byte_rate: 0,
+            block_align,
+            bits_per_sample: 4,
+            extension_size: Some(2),
+            extensible: None,
+            extra: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_decodes_ima_blocks() -> Result<()> {
+        let format = ima_format(1, 8);
+        // Starts at 100 with step index 0, which the nibbles move to 2, 1 and 9.
+        let block = [100, 0, 0, 0, 0x84, 0x07, 0x00, 0x00];
+        let samples = decode_ima(&format, &block)?;
+        assert_eq!(samples.len(), 9);
+        assert_eq!(samples[..5], [100, 107, 106, 121, 123]);
+
+        // A short final block holds fewer frames.
+        let samples = decode_ima(&format, &[block, block].concat()[..12])?;
+        assert_eq!(samples.len(), 9 + 1);
+        Ok(())
+    }
+
+    #[test]
+    fn it_round_trips_ima_samples() -> Result<()> {
+        let mut format = ima_format(2, 0);
+        set_block_layout(&mut format)?;
+        assert_eq!(format.block_align, 512);
+        assert_eq!(format.extra, 505u16.to_le_bytes());
+
+        let input: Vec<i16> = (0..2 * 600)
+            .map(|index| ((index / 2) as f64 * 0.05).sin() * 8000.0 * (1 - index % 2 * 2) as f64)
+            .map(|value| value as i16)
+            .collect();
+        let mut data = Vec::new();
+        encode_ima(&format, &input, &mut data)?;
+        assert_eq!(data.len(), 512 + 8 + 12 * 8);
+
+        let output = decode_ima(&format, &data)?;
+        assert_eq!(output.len(), 2 * (505 + 1 + 12 * 8));
+        // Skip the first frames, while the step size adapts to the signal.
+        for (input, output) in input.iter().zip(&output).skip(32) {
+            assert!((*input as i32 - *output as i32).abs() < 500);
+        }
+        Ok(())
+    }
+
+    fn ms_format(num_channels: u16) -> FormatChunk {
+        let mut format = FormatChunk {
+            size: 0,
+            audio_format: WaveFormat::MsAdpcm,
+            num_channels,
+            sample_rate: 8000,
+            byte_rate: 0,
+            block_align: 0,
+            bits_per_sample: 4,
+            extension_size: None,
+            extensible: None,
+            extra: Vec::new(),
+        };
+        set_block_layout(&mut format).unwrap();
+        format
+    }
+
+    #[test]
+    fn it_parses_ms_adpcm_coefficients() {
+        let format = ms_format(1);
+        assert_eq!(format.size, 50);
+        assert_eq!(format.extension_size, Some(32));
+        assert_eq!(format.block_align, 256);
+        assert_eq!(format.byte_rate, 4096);
+
+        let ms_format = MsAdpcmFormat::parse(&format.extra).unwrap();
+        assert_eq!(ms_format, MsAdpcmFormat::standard(500));
+        assert_eq!(
+            ms_format.coefficients[5],
+            MsAdpcmCoefficient {
+                coef1: 460,
+                coef2: -208
+            }
+        );
+        assert_eq!(MsAdpcmFormat::parse(&format.extra[..31]), None);
+    }
+
+    #[test]
+    fn it_decodes_ms_blocks() -> Result<()> {
+        let format = ms_format(1);
+        // Coefficients 256 and 0 repeat the previous sample, then the nibbles add 1 and -2 deltas.
+        let block = [0, 16, 0, 100, 0, 50, 0, 0x1E];
+        let samples = decode_ms(&format, &block)?;
+        assert_eq!(samples, [50, 100, 116, 84]);
+
+        let mut bad = block;
+        bad[0] = 7;
+        assert!(decode_ms(&format, &bad).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn it_caps_ms_deltas() -> Result<()> {
+        let format = ms_format(1);
+        // The largest header delta, which every nibble of 7 triples.
+        let mut block = vec![0, 0xFF, 0x7F, 0, 0, 0, 0];
+        block.extend_from_slice(&[0x77; 16]);
+        let samples = decode_ms(&format, &block)?;
+        assert_eq!(samples.len(), 2 + 32);
+        assert_eq!(samples[2..], [i16::MAX; 32]);
+
+        // Custom coefficients at the extremes, with samples at the extremes.
+        let mut format = format;
+        format.extra[4..8].copy_from_slice(&[0x00, 0x80, 0x00, 0x80]);
+        let block = [0, 16, 0, 0x00, 0x80, 0x00, 0x80, 0x00];
+        assert_eq!(decode_ms(&format, &block)?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn it_round_trips_ms_samples() -> Result<()> {
+        let format = ms_format(2);
+        assert_eq!(format.block_align, 512);
+
+        let input: Vec<i16> = (0..2 * 600)
+            .map(|index| ((index / 2) as f64 * 0.05).sin() * 8000.0 * (1 - index % 2 * 2) as f64)
+            .map(|value| value as i16)
+            .collect();
+        let mut data = Vec::new();
+        encode_ms(&format, &input, &mut data)?;
+        assert_eq!(data.len(), 512 + 14 + 98);
+
+        let output = decode_ms(&format, &data)?;
+        assert_eq!(output.len(), input.len());
+        // Skip the first frames, while the delta adapts to the signal.
+        for (input, output) in input.iter().zip(&output).skip(32) {
+            assert!((*input as i32 - *output as i32).abs() < 500);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+//! The bext chunk of the EBU Tech 3285 Broadcast Wave Format.
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::{io, text, Result};
+
+/// Length of the fields preceding the coding history.
+const FIXED_LEN: usize = 602;
+
+/// Production metadata of a Broadcast Wave Format file.
+///
+/// Text fields are Latin-1, and are limited to the width of their field in the file. The
+/// loudness fields are in hundredths of a unit, and are only defined from version 2 onwards. A
+/// chunk that is not modified is written back as it was read.
+#[derive(Debug, Clone)]
+pub struct BextChunk {
+    /// Up to 256 characters.
+    pub description: String,
+    /// The name of the originator, up to 32 characters.
+    pub originator: String,
+    /// A reference assigned by the originator, up to 32 characters.
+    pub originator_reference: String,
+    /// `yyyy-mm-dd`, or empty.
+    pub origination_date: String,
+    /// `hh:mm:ss`, or empty.
+    pub origination_time: String,
+    /// The sample frame the audio starts at, counted from midnight.
+    pub time_reference: u64,
+    pub version: u16,
+    /// The SMPTE 330M UMID, all zero when there is none.
+    pub umid: [u8; 64],
+    /// Integrated loudness in LUFS.
+    pub loudness_value: i16,
+    /// Loudness range in LU.
+    pub loudness_range: i16,
+    /// Maximum true peak level in dBTP.
+    pub max_true_peak_level: i16,
+    /// Highest momentary loudness in LUFS.
+    pub max_momentary_loudness: i16,
+    /// Highest short-term loudness in LUFS.
+    pub max_short_term_loudness: i16,
+    /// The reserved bytes after the loudness fields, written back as they were read.
+    pub reserved: [u8; 180],
+    /// Lines ending in CR LF, one for each process the audio went through.
+    pub coding_history: String,
+    /// The body of the chunk as read, empty for new chunks.
+    raw: Vec<u8>,
+}
+
+impl PartialEq for BextChunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.description == other.description
+            && self.originator == other.originator
+            && self.originator_reference == other.originator_reference
+            && self.origination_date == other.origination_date
+            && self.origination_time == other.origination_time
+            && self.time_reference == other.time_reference
+            && self.version == other.version
+            && self.umid == other.umid
+            && self.loudness_value == other.loudness_value
+            && self.loudness_range == other.loudness_range
+            && self.max_true_peak_level == other.max_true_peak_level
+            && self.max_momentary_loudness == other.max_momentary_loudness
+            && self.max_short_term_loudness == other.max_short_term_loudness
+            && self.reserved == other.reserved
+            && self.coding_history == other.coding_history
+    }
+}
+
+impl Default for BextChunk {
+    fn default() -> Self {
+        BextChunk {
+            description: String::new(),
+            originator: String::new(),
+            originator_reference: String::new(),
+            origination_date: String::new(),
+            origination_time: String::new(),
+            time_reference: 0,
+            version: 2,
+            umid: [0; 64],
+            loudness_value: 0,
+            loudness_range: 0,
+            max_true_peak_level: 0,
+            max_momentary_loudness: 0,
+            max_short_term_loudness: 0,
+            reserved: [0; 180],
+            coding_history: String::new(),
+            raw: Vec::new(),
+        }
+    }
+}
+
+impl BextChunk {
+    /// Parses the body of a bext chunk. Returns `None` if it is shorter than its fixed fields.
+    pub(crate) fn parse(data: &[u8]) -> Option<BextChunk> {
+        if data.len() < FIXED_LEN {
+            return None;
+        }
+        let u16_at = |offset: usize| u16::from_le_bytes([data[offset], data[offset + 1]]);
+        let mut time_reference = [0; 8];
+        time_reference.copy_from_slice(&data[338..346]);
+        let mut umid = [0; 64];
+        umid.copy_from_slice(&data[348..412]);
+        let mut reserved = [0; 180];
+        reserved.copy_from_slice(&data[422..FIXED_LEN]);
+
+        Some(BextChunk {
+            description: text::field(&data[..256]),
+            originator: text::field(&data[256..288]),
+            originator_reference: text::field(&data[288..320]),
+            origination_date: text::field(&data[320..330]),
+            origination_time: text::field(&data[330..338]),
+            time_reference: u64::from_le_bytes(time_reference),
+            version: u16_at(346),
+            umid,
+            loudness_value: u16_at(412) as i16,
+            loudness_range: u16_at(414) as i16,
+            max_true_peak_level: u16_at(416) as i16,
+            max_momentary_loudness: u16_at(418) as i16,
+            max_short_term_loudness: u16_at(420) as i16,
+            reserved,
+            coding_history: text::field(&data[FIXED_LEN..]),
+            raw: data.to_vec(),
+        })
+    }
+
+    /// Checks that every text field is Latin-1 and fits its field, which text read from a file
+    /// always does.
+    pub fn validate(&self) -> Result<()> {
+        let fields = [
+            (&self.description, 256),
+            (&self.originator, 32),
+            (&self.originator_reference, 32),
+            (&self.origination_date, 10),
+            (&self.origination_time, 8),
+            (&self.coding_history, usize::MAX),
+        ];
+        for (value, width) in fields {
+            if !text::fits(value, width) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "bext text does not fit its field.",
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// The body of the chunk, which for a modified chunk has its text padded with NUL. Text longer
+    /// than its field is cut off, see [`BextChunk::validate`].
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        if BextChunk::parse(&self.raw).as_ref() == Some(self) {
+            return self.raw.clone();
+        }
+        let mut data = Vec::with_capacity(FIXED_LEN + self.coding_history.len());
+        text::push_field(&mut data, &self.description, 256);
+        text::push_field(&mut data, &self.originator, 32);
+        text::push_field(&mut data, &self.originator_reference, 32);
+        text::push_field(&mut data, &self.origination_date, 10);
+        text::push_field(&mut data, &self.origination_time, 8);
+        data.extend_from_slice(&self.time_reference.to_le_bytes());
+        data.extend_from_slice(&self.version.to_le_bytes());
+        data.extend_from_slice(&self.umid);
+        for value in [
+            self.loudness_value,
+            self.loudness_range,
+            self.max_true_peak_level,
+            self.max_momentary_loudness,
+            self.max_short_term_loudness,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(&self.reserved);
+        text::push_latin1(&mut data, &self.coding_history);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_fixed_fields() -> Result<()> {
+        let mut bext = BextChunk {
+            description: "Scène 12, take 3".into(),
+            originator: "Recorder".into(),
+            originator_reference: "USREC0000000001".into(),
+            origination_date: "2024-05-01".into(),
+            origination_time: "13:45:00".into(),
+            time_reference: 0x1_0000_0001,
+            umid: [7; 64],
+            loudness_value: -2300,
+            max_true_peak_level: -100,
+            coding_history: "A=PCM,F=48000,W=24,M=stereo\r\n".into(),
+            ..BextChunk::default()
+        };
+        bext.reserved[179] = 1;
+        bext.validate()?;
+        let data = bext.to_bytes();
+        assert_eq!(data.len(), 602 + 29);
+        assert_eq!(data[..6], *b"Sc\xe8ne ");
+        assert_eq!(data[338..346], [1, 0, 0, 0, 1, 0, 0, 0]);
+        assert_eq!(data[601], 1);
+        assert_eq!(BextChunk::parse(&data), Some(bext));
+        assert_eq!(BextChunk::parse(&data[..600]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_back_unmodified_chunks() {
+        let mut data = [0; 602].to_vec();
+        data[..5].copy_from_slice(b"ab\0cd");
+        data.extend_from_slice(b"A=PCM\r\n\0\0\0\0");
+        let mut bext = BextChunk::parse(&data).unwrap();
+        assert_eq!(bext.description, "ab");
+        assert_eq!(bext.coding_history, "A=PCM\r\n");
+        assert_eq!(bext.to_bytes(), data);
+
+        bext.originator = "Recorder".into();
+        let modified = bext.to_bytes();
+        assert_eq!(modified.len(), 602 + 7);
+        assert_eq!(modified[..5], *b"ab\0\0\0");
+        assert_eq!(BextChunk::parse(&modified), Some(bext));
+    }
+
+    #[test]
+    fn it_validates_field_widths() {
+        let mut bext = BextChunk {
+            originator: "x".repeat(33),
+            ..BextChunk::default()
+        };
+        assert!(bext.validate().is_err());
+        bext.originator = "x".repeat(32);
+        assert!(bext.validate().is_ok());
+        bext.origination_date = "2024-05-01Z".into();
+        assert!(bext.validate().is_err());
+        bext.origination_date = "2024-05-01".into();
+        bext.description = "é".into();
+        assert!(bext.validate().is_ok());
+        bext.description = "€".into();
+        assert!(bext.validate().is_err());
+    }
+}
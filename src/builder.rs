@@ -0,0 +1,311 @@
+//! Construction of new WAV files from samples.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::{adpcm, sample};
+use crate::{
+    io, ChannelMask, ChunkOrder, Container, DataChunk, ExtensibleFormat, FactChunk, FormatChunk,
+    PeakChunk, Result, Sample, SubFormat, Wave, WaveFormat, WaveWriter,
+};
+
+/// Builds a [`Wave`] from samples, deriving the size, rate and alignment fields of its chunks.
+///
+/// A FACT chunk holding the number of sample frames is added for every format other than PCM,
+/// and a PEAK chunk is added when requested with [`WaveBuilder::peak`]. Setting the valid bits or
+/// the channel mask writes the format as [`WaveFormat::Extensible`]. IMA and MS ADPCM are
+/// written with 4 bits per sample, in blocks of the size encoders commonly use for the sample
+/// rate. MS ADPCM uses the standard coefficients.
+///
+/// ```
+/// use waverly::{WaveBuilder, WaveFormat};
+///
+/// // One second of silence in 16-bit stereo.
+/// let samples = vec![0i16; 2 * 44100];
+/// let wave = WaveBuilder::new(44100, 2, WaveFormat::Pcm, 16).build(&samples)?;
+/// assert_eq!(wave.format.byte_rate, 176400);
+/// # Ok::<(), waverly::WaverlyError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WaveBuilder {
+    sample_rate: u32,
+    num_channels: u16,
+    audio_format: WaveFormat,
+    bits_per_sample: u16,
+    valid_bits_per_sample: Option<u16>,
+    channel_mask: Option<ChannelMask>,
+    peak_timestamp: Option<u32>,
+    chunk_order: ChunkOrder,
+    container: Container,
+}
+
+impl WaveBuilder {
+    pub fn new(
+        sample_rate: u32,
+        num_channels: u16,
+        audio_format: WaveFormat,
+        bits_per_sample: u16,
+    ) -> Self {
+        WaveBuilder {
+            sample_rate,
+            num_channels,
+            audio_format,
+            bits_per_sample,
+            valid_bits_per_sample: None,
+            channel_mask: None,
+            peak_timestamp: None,
+            chunk_order: ChunkOrder::default(),
+            container: Container::default(),
+        }
+    }
+
+    /// Stores only the `bits` most significant bits of each sample, in a container of
+    /// `bits_per_sample` bits, such as 20-bit samples in a 24-bit container.
+    pub fn valid_bits(mut self, bits: u16) -> Self {
+        self.valid_bits_per_sample = Some(bits);
+        self
+    }
+
+    /// Sets the speaker position of each channel.
+    pub fn channel_mask(mut self, mask: ChannelMask) -> Self {
+        self.channel_mask = Some(mask);
+        self
+    }
+
+    /// Adds a PEAK chunk computed from the samples, dated `timestamp` seconds after the Unix
+    /// epoch.
+    pub fn peak(mut self, timestamp: u32) -> Self {
+        self.peak_timestamp = Some(timestamp);
+        self
+    }
+
+    /// Sets where the data chunk is written relative to the other chunks.
+    pub fn chunk_order(mut self, order: ChunkOrder) -> Self {
+        self.chunk_order = order;
+        self
+    }
+
+    /// Sets the outermost chunk. Files are written as RF64 once they exceed 4 GiB regardless.
+    pub fn container(mut self, container: Container) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// Encodes `samples`, interleaved by channel, into a new [`Wave`].
+    pub fn build<S: Sample>(self, samples: &[S]) -> Result<Wave> {
+        let format = self.format()?;
+        let num_channels = self.num_channels as usize;
+        if !samples.len().is_multiple_of(num_channels) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Samples must contain a whole number of frames.",
+            )
+            .into());
+        }
+
+        let mut data = Vec::new();
+        sample::encode(&format, samples, &mut data)?;
+
+        let fact = if self.audio_format != WaveFormat::Pcm {
+            Some(FactChunk {
+                size: 4,
+                data: (samples.len() / num_channels) as u32,
+                trailing: Vec::new(),
+            })
+        } else {
+            None
+        };
+
+        let peak = match self.peak_timestamp {
+            Some(timestamp) => Some(PeakChunk::from_samples(&format, &data, timestamp)?),
+            None => None,
+        };
+
+        Ok(Wave {
+            container: self.container,
+            // Recomputed by `Wave::write` once the length of every chunk is known.
+            ds64: None,
+            format,
+            data: DataChunk {
+                size: data.len() as u64,
+                data,
+            },
+            fact,
+            peak,
+            bext: None,
+            cart: None,
+            ixml: None,
+            info: None,
+            id3: None,
+            cue: None,
+            playlist: None,
+            associated_data: None,
+            sampler: None,
+            instrument: None,
+            acid: None,
+            chna: None,
+            axml: None,
+            unknown: Vec::new(),
+            order: self.chunk_order.layout(0),
+        })
+    }
+
+    /// Writes the header of a new file to `writer`, returning a [`WaveWriter`] that samples can
+    /// be written to as they become available. The chunk order is ignored, as the data chunk is
+    /// always written last.
+    pub fn writer<W: io::Write + io::Seek>(self, writer: W) -> Result<WaveWriter<W>> {
+        let format = self.format()?;
+        WaveWriter::start(writer, format, self.container, self.peak_timestamp)
+    }
+
+    /// The format chunk, with its size, rate and alignment fields derived from the settings.
+    fn format(&self) -> Result<FormatChunk> {
+        let num_channels = self.num_channels as usize;
+        if num_channels == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Channel count must be non-zero.",
+            )
+            .into());
+        }
+
+        let mut format = FormatChunk {
+            size: 16,
+            audio_format: self.audio_format,
+            num_channels: self.num_channels,
+            sample_rate: self.sample_rate,
+            byte_rate: 0,
+            block_align: 0,
+            bits_per_sample: self.bits_per_sample,
+            extension_size: None,
+            extensible: None,
+            extra: Vec::new(),
+        };
+        if self.valid_bits_per_sample.is_some() || self.channel_mask.is_some() {
+            let sub_format = match self.audio_format {
+                WaveFormat::Pcm => SubFormat::Pcm,
+                WaveFormat::IeeeFloat => SubFormat::IeeeFloat,
+                WaveFormat::Alaw => SubFormat::Alaw,
+                WaveFormat::Mulaw => SubFormat::Mulaw,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "Only PCM, IEEE float, A-law and µ-law can be written as extensible.",
+                    )
+                    .into())
+                }
+            };
+            format.size = 40;
+            format.audio_format = WaveFormat::Extensible;
+            format.extension_size = Some(22);
+            format.extensible = Some(ExtensibleFormat {
+                valid_bits_per_sample: self.valid_bits_per_sample.unwrap_or(self.bits_per_sample),
+                channel_mask: self.channel_mask.unwrap_or_default(),
+                sub_format_guid: sub_format.guid(),
+            });
+        }
+        if let WaveFormat::ImaAdpcm | WaveFormat::MsAdpcm = self.audio_format {
+            adpcm::set_block_layout(&mut format)?;
+        } else {
+            let block_align = num_channels * sample::bytes_per_sample(&format)?;
+            let too_large = || {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Frame size or byte rate does not fit the format chunk.",
+                )
+            };
+            format.block_align = u16::try_from(block_align).map_err(|_| too_large())?;
+            format.byte_rate = self
+                .sample_rate
+                .checked_mul(block_align as u32)
+                .ok_or_else(too_large)?;
+        }
+        Ok(format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Peak;
+
+    #[test]
+    fn it_derives_format_fields() -> Result<()> {
+        let samples = [0i32, 1 << 16, -1 << 16, 1 << 24, 0, 0];
+        let wave = WaveBuilder::new(48000, 3, WaveFormat::Pcm, 24).build(&samples)?;
+
+        assert_eq!(wave.format.block_align, 9);
+        assert_eq!(wave.format.byte_rate, 432000);
+        assert_eq!(wave.data.size, 18);
+        assert_eq!(wave.fact, None);
+        assert_eq!(wave.peak, None);
+        Ok(())
+    }
+
+    #[test]
+    fn it_computes_fact_and_peak() -> Result<()> {
+        let samples = [0.25f32, -0.5, -0.75, 0.5, 0.0, 0.0];
+        let wave = WaveBuilder::new(8000, 2, WaveFormat::IeeeFloat, 32)
+            .peak(1_600_000_000)
+            .build(&samples)?;
+
+        assert_eq!(wave.fact.map(|fact| fact.data), Some(3));
+        let peak = wave.peak.unwrap();
+        assert_eq!(peak.timestamp, 1_600_000_000);
+        assert_eq!(
+            peak.peaks,
+            [
+                Peak {
+                    value: 0.75,
+                    position: 1
+                },
+                Peak {
+                    value: 0.5,
+                    position: 0
+                }
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_builds_extensible_formats() -> Result<()> {
+        let samples = [i32::MIN, 0, 0x7FFF_FFFF, 1 << 12];
+        let wave = WaveBuilder::new(48000, 2, WaveFormat::Pcm, 24)
+            .valid_bits(20)
+            .channel_mask(ChannelMask::STEREO)
+            .build(&samples)?;
+
+        assert_eq!(wave.format.audio_format, WaveFormat::Extensible);
+        assert_eq!(wave.format.size, 40);
+        assert_eq!(wave.format.block_align, 6);
+        assert_eq!(wave.fact, None);
+        let extensible = wave.format.extensible.as_ref().unwrap();
+        assert_eq!(extensible.sub_format(), SubFormat::Pcm);
+        assert_eq!(
+            wave.samples::<i32>()?.collect::<Vec<_>>(),
+            [i32::MIN, 0, 0x7FFF_F000, 1 << 12]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_rejects_partial_frames() {
+        let builder = WaveBuilder::new(8000, 2, WaveFormat::Pcm, 16);
+        assert!(builder.build(&[0i16; 3]).is_err());
+    }
+
+    #[test]
+    fn it_rejects_frames_too_large_for_the_format() {
+        let builder = WaveBuilder::new(8000, u16::MAX, WaveFormat::IeeeFloat, 64);
+        assert!(builder.build(&[0f64; 0]).is_err());
+        let builder = WaveBuilder::new(u32::MAX, 2, WaveFormat::Pcm, 16);
+        assert!(builder.build(&[0i16; 0]).is_err());
+    }
+
+    #[test]
+    fn it_rejects_adpcm_blocks_too_large_for_the_format() {
+        let builder = WaveBuilder::new(44100, 64, WaveFormat::ImaAdpcm, 4);
+        assert!(builder.build(&[0i16; 64]).is_err());
+    }
+}
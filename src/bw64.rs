@@ -0,0 +1,166 @@
+//! The chna chunk of ITU-R BS.2088 BW64 files, which links tracks to their ADM metadata.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use binrw::{binrw, BinReaderExt};
+
+use crate::{io, Result};
+
+/// The track UIDs of a file, each linking a track to the audioTrackUID, audioTrackFormat and
+/// audioPackFormat ids of the ADM metadata in the axml chunk.
+///
+/// Entries with a track index of zero are unused, and are kept so that files can reserve room
+/// for entries to be added later.
+#[binrw]
+#[brw(magic = b"chna")]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ChnaChunk {
+    #[br(little)]
+    pub size: u32,
+    /// The number of distinct tracks of the used entries.
+    #[br(little)]
+    pub num_tracks: u16,
+    /// The number of used entries.
+    #[br(little)]
+    pub num_uids: u16,
+    #[br(count = size.saturating_sub(4) / 40)]
+    pub audio_ids: Vec<AudioId>,
+}
+
+impl ChnaChunk {
+    /// Parses a chna chunk, including its id and size. Returns `None` unless the chunk holds a
+    /// whole number of entries.
+    pub(crate) fn parse(chunk: &[u8]) -> Option<ChnaChunk> {
+        let entries = chunk.len().checked_sub(12)?;
+        if entries % 40 != 0 {
+            return None;
+        }
+        crate::parse_whole(chunk, |cursor| cursor.read_le())
+    }
+
+    /// The used entries of a track, which is numbered from 1.
+    pub fn track(&self, track_index: u16) -> impl Iterator<Item = &AudioId> {
+        self.audio_ids
+            .iter()
+            .filter(move |audio_id| audio_id.track_index == track_index)
+    }
+
+    /// Updates `size`, `num_tracks` and `num_uids` to match `audio_ids`.
+    pub(crate) fn update_size(&mut self) {
+        let mut tracks: Vec<u16> = self
+            .audio_ids
+            .iter()
+            .map(|audio_id| audio_id.track_index)
+            .filter(|&track_index| track_index != 0)
+            .collect();
+        self.num_uids = tracks.len() as u16;
+        tracks.sort_unstable();
+        tracks.dedup();
+        self.num_tracks = tracks.len() as u16;
+        self.size = 4 + 40 * self.audio_ids.len() as u32;
+    }
+}
+
+/// An entry of a [`ChnaChunk`]. The ids are ASCII, such as `ATU_00000001`,
+/// `AT_00031001_01` and `AP_00031001`.
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioId {
+    /// The track the entry belongs to, numbered from 1. Zero marks an unused entry.
+    #[br(little)]
+    pub track_index: u16,
+    pub uid: [u8; 12],
+    pub track_ref: [u8; 14],
+    pub pack_ref: [u8; 11],
+    pub padding: u8,
+}
+
+impl AudioId {
+    /// Fails unless each id has exactly the length of its field.
+    pub fn new(track_index: u16, uid: &str, track_ref: &str, pack_ref: &str) -> Result<AudioId> {
+        Ok(AudioId {
+            track_index,
+            uid: fixed(uid)?,
+            track_ref: fixed(track_ref)?,
+            pack_ref: fixed(pack_ref)?,
+            padding: 0,
+        })
+    }
+
+    pub fn uid(&self) -> &str {
+        ascii(&self.uid)
+    }
+
+    pub fn track_ref(&self) -> &str {
+        ascii(&self.track_ref)
+    }
+
+    pub fn pack_ref(&self) -> &str {
+        ascii(&self.pack_ref)
+    }
+}
+
+fn fixed<const N: usize>(id: &str) -> Result<[u8; N]> {
+    id.as_bytes().try_into().map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "ADM id does not match the length of its field.",
+        )
+        .into()
+    })
+}
+
+/// The id up to the first byte that is not ASCII, such as the NUL bytes of an unused entry.
+fn ascii(id: &[u8]) -> &str {
+    let len = id
+        .iter()
+        .position(|byte| !byte.is_ascii() || *byte == 0)
+        .unwrap_or(id.len());
+    core::str::from_utf8(&id[..len]).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_counts_used_entries() -> Result<()> {
+        let mut chna = ChnaChunk::default();
+        chna.audio_ids.push(AudioId::new(
+            1,
+            "ATU_00000001",
+            "AT_00010001_01",
+            "AP_00010002",
+        )?);
+        chna.audio_ids.push(AudioId::new(
+            2,
+            "ATU_00000002",
+            "AT_00010002_01",
+            "AP_00010002",
+        )?);
+        chna.audio_ids.push(AudioId::new(
+            1,
+            "ATU_00000003",
+            "AT_00031001_01",
+            "AP_00031001",
+        )?);
+        chna.audio_ids.push(AudioId {
+            track_index: 0,
+            uid: [0; 12],
+            track_ref: [0; 14],
+            pack_ref: [0; 11],
+            padding: 0,
+        });
+        chna.update_size();
+
+        assert_eq!(chna.size, 4 + 4 * 40);
+        assert_eq!(chna.num_tracks, 2);
+        assert_eq!(chna.num_uids, 3);
+        let uids: Vec<&str> = chna.track(1).map(AudioId::uid).collect();
+        assert_eq!(uids, ["ATU_00000001", "ATU_00000003"]);
+        assert_eq!(chna.audio_ids[3].pack_ref(), "");
+        assert!(AudioId::new(1, "ATU_1", "AT_00010001_01", "AP_00010002").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,309 @@
+//! The cart chunk of AES46, which radio automation systems use to schedule and play cuts.
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::{io, text, Result};
+
+/// Length of the fields preceding the tag text.
+const FIXED_LEN: usize = 2048;
+
+/// Broadcast cart metadata, such as the title and the dates a cut may be aired between.
+///
+/// The text fields are stored like those of a [`BextChunk`](crate::BextChunk), and the chunk is
+/// likewise written back as it was read until it is modified.
+#[derive(Debug, Clone)]
+pub struct CartChunk {
+    /// Four digits, `0101` for version 1.01.
+    pub version: String,
+    /// Up to 64 characters.
+    pub title: String,
+    /// Up to 64 characters.
+    pub artist: String,
+    /// The cut number the automation system knows the audio by, up to 64 characters.
+    pub cut_id: String,
+    /// Up to 64 characters.
+    pub client_id: String,
+    /// Up to 64 characters.
+    pub category: String,
+    /// Up to 64 characters.
+    pub classification: String,
+    /// The last words of the cut, up to 64 characters.
+    pub out_cue: String,
+    /// `yyyy-mm-dd`, or empty.
+    pub start_date: String,
+    /// `hh:mm:ss`, or empty.
+    pub start_time: String,
+    /// `yyyy-mm-dd`, or empty.
+    pub end_date: String,
+    /// `hh:mm:ss`, or empty.
+    pub end_time: String,
+    /// The application that wrote the chunk, up to 64 characters.
+    pub producer_app_id: String,
+    /// Up to 64 characters.
+    pub producer_app_version: String,
+    /// Up to 64 characters.
+    pub user_def: String,
+    /// The sample value of 0 dB reference, or 0 when the level is unknown.
+    pub level_reference: i32,
+    pub post_timers: [PostTimer; 8],
+    /// Reserved by AES46 for later versions.
+    pub reserved: [u8; 276],
+    /// Up to 1024 characters.
+    pub url: String,
+    /// Free text, in lines ending in CR LF.
+    pub tag_text: String,
+    /// The body of the chunk as read, empty for new chunks.
+    raw: Vec<u8>,
+}
+
+impl PartialEq for CartChunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.title == other.title
+            && self.artist == other.artist
+            && self.cut_id == other.cut_id
+            && self.client_id == other.client_id
+            && self.category == other.category
+            && self.classification == other.classification
+            && self.out_cue == other.out_cue
+            && self.start_date == other.start_date
+            && self.start_time == other.start_time
+            && self.end_date == other.end_date
+            && self.end_time == other.end_time
+            && self.producer_app_id == other.producer_app_id
+            && self.producer_app_version == other.producer_app_version
+            && self.user_def == other.user_def
+            && self.level_reference == other.level_reference
+            && self.post_timers == other.post_timers
+            && self.reserved == other.reserved
+            && self.url == other.url
+            && self.tag_text == other.tag_text
+    }
+}
+
+/// A marker in a [`CartChunk`], such as the end of the intro.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PostTimer {
+    /// What the timer marks, such as `SEC1` or `EOD `. All zero for an unused timer.
+    pub usage: [u8; 4],
+    /// The sample frame the timer is at.
+    pub value: u32,
+}
+
+impl Default for CartChunk {
+    fn default() -> Self {
+        CartChunk {
+            version: "0101".into(),
+            title: String::new(),
+            artist: String::new(),
+            cut_id: String::new(),
+            client_id: String::new(),
+            category: String::new(),
+            classification: String::new(),
+            out_cue: String::new(),
+            start_date: String::new(),
+            start_time: String::new(),
+            end_date: String::new(),
+            end_time: String::new(),
+            producer_app_id: String::new(),
+            producer_app_version: String::new(),
+            user_def: String::new(),
+            level_reference: 0,
+            post_timers: [PostTimer::default(); 8],
+            reserved: [0; 276],
+            url: String::new(),
+            tag_text: String::new(),
+            raw: Vec::new(),
+        }
+    }
+}
+
+impl CartChunk {
+    /// Parses the body of a cart chunk. Returns `None` if it is shorter than its fixed fields.
+    pub(crate) fn parse(data: &[u8]) -> Option<CartChunk> {
+        if data.len() < FIXED_LEN {
+            return None;
+        }
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes([
+                data[offset],
+                data[offset + 1],
+                data[offset + 2],
+                data[offset + 3],
+            ])
+        };
+        let mut post_timers = [PostTimer::default(); 8];
+        for (index, timer) in post_timers.iter_mut().enumerate() {
+            let offset = 684 + 8 * index;
+            timer.usage.copy_from_slice(&data[offset..offset + 4]);
+            timer.value = u32_at(offset + 4);
+        }
+        let mut reserved = [0; 276];
+        reserved.copy_from_slice(&data[748..1024]);
+
+        Some(CartChunk {
+            version: text::field(&data[..4]),
+            title: text::field(&data[4..68]),
+            artist: text::field(&data[68..132]),
+            cut_id: text::field(&data[132..196]),
+            client_id: text::field(&data[196..260]),
+            category: text::field(&data[260..324]),
+            classification: text::field(&data[324..388]),
+            out_cue: text::field(&data[388..452]),
+            start_date: text::field(&data[452..462]),
+            start_time: text::field(&data[462..470]),
+            end_date: text::field(&data[470..480]),
+            end_time: text::field(&data[480..488]),
+            producer_app_id: text::field(&data[488..552]),
+            producer_app_version: text::field(&data[552..616]),
+            user_def: text::field(&data[616..680]),
+            level_reference: u32_at(680) as i32,
+            post_timers,
+            reserved,
+            url: text::field(&data[1024..FIXED_LEN]),
+            tag_text: text::field(&data[FIXED_LEN..]),
+            raw: data.to_vec(),
+        })
+    }
+
+    /// Fails if a text field holds more characters than its width, or characters outside of
+    /// Latin-1.
+    pub fn validate(&self) -> Result<()> {
+        let fields = [
+            (&self.version, 4),
+            (&self.title, 64),
+            (&self.artist, 64),
+            (&self.cut_id, 64),
+            (&self.client_id, 64),
+            (&self.category, 64),
+            (&self.classification, 64),
+            (&self.out_cue, 64),
+            (&self.start_date, 10),
+            (&self.start_time, 8),
+            (&self.end_date, 10),
+            (&self.end_time, 8),
+            (&self.producer_app_id, 64),
+            (&self.producer_app_version, 64),
+            (&self.user_def, 64),
+            (&self.url, 1024),
+            (&self.tag_text, usize::MAX),
+        ];
+        for (value, width) in fields {
+            if !text::fits(value, width) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "cart text does not fit its field.",
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    /// The body of the chunk, rebuilt from the fields once they no longer match the body as read.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        if CartChunk::parse(&self.raw).as_ref() == Some(self) {
+            return self.raw.clone();
+        }
+        let mut data = Vec::with_capacity(FIXED_LEN + self.tag_text.len());
+        text::push_field(&mut data, &self.version, 4);
+        for value in [
+            &self.title,
+            &self.artist,
+            &self.cut_id,
+            &self.client_id,
+            &self.category,
+            &self.classification,
+            &self.out_cue,
+        ] {
+            text::push_field(&mut data, value, 64);
+        }
+        text::push_field(&mut data, &self.start_date, 10);
+        text::push_field(&mut data, &self.start_time, 8);
+        text::push_field(&mut data, &self.end_date, 10);
+        text::push_field(&mut data, &self.end_time, 8);
+        text::push_field(&mut data, &self.producer_app_id, 64);
+        text::push_field(&mut data, &self.producer_app_version, 64);
+        text::push_field(&mut data, &self.user_def, 64);
+        data.extend_from_slice(&self.level_reference.to_le_bytes());
+        for timer in &self.post_timers {
+            data.extend_from_slice(&timer.usage);
+            data.extend_from_slice(&timer.value.to_le_bytes());
+        }
+        data.extend_from_slice(&self.reserved);
+        text::push_field(&mut data, &self.url, 1024);
+        text::push_latin1(&mut data, &self.tag_text);
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_fixed_fields() -> Result<()> {
+        let mut cart = CartChunk {
+            title: "Café jingle".into(),
+            artist: "Station".into(),
+            cut_id: "10452".into(),
+            start_date: "2024-05-01".into(),
+            start_time: "00:00:00".into(),
+            end_date: "9999-12-31".into(),
+            end_time: "23:59:59".into(),
+            level_reference: 32768,
+            url: "https://example.com/cuts/10452".into(),
+            tag_text: "Top of the hour\r\n".into(),
+            ..CartChunk::default()
+        };
+        cart.post_timers[0] = PostTimer {
+            usage: *b"SEC1",
+            value: 44100,
+        };
+        cart.reserved[0] = 1;
+        cart.validate()?;
+        let data = cart.to_bytes();
+        assert_eq!(data.len(), 2048 + 17);
+        assert_eq!(data[4..9], *b"Caf\xe9 ");
+        assert_eq!(data[684..692], *b"SEC1\x44\xAC\x00\x00");
+        assert_eq!(data[748], 1);
+        assert_eq!(data[1024..1029], *b"https");
+        assert_eq!(CartChunk::parse(&data), Some(cart));
+        assert_eq!(CartChunk::parse(&data[..2000]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_back_unmodified_chunks() {
+        let mut data = [0; 2048].to_vec();
+        data[4..9].copy_from_slice(b"ab\0cd");
+        data.extend_from_slice(b"Intro\r\n\0\0\0");
+        let mut cart = CartChunk::parse(&data).unwrap();
+        assert_eq!(cart.title, "ab");
+        assert_eq!(cart.tag_text, "Intro\r\n");
+        assert_eq!(cart.to_bytes(), data);
+
+        cart.artist = "Station".into();
+        let modified = cart.to_bytes();
+        assert_eq!(modified.len(), 2048 + 7);
+        assert_eq!(modified[4..9], *b"ab\0\0\0");
+        assert_eq!(CartChunk::parse(&modified), Some(cart));
+    }
+
+    #[test]
+    fn it_validates_fixed_fields() {
+        let mut cart = CartChunk {
+            title: "x".repeat(65),
+            ..CartChunk::default()
+        };
+        assert!(cart.validate().is_err());
+        cart.title = "x".repeat(64);
+        assert!(cart.validate().is_ok());
+        cart.version = "01.01".into();
+        assert!(cart.validate().is_err());
+        cart.version = "0101".into();
+        cart.tag_text = "\u{266a}".into();
+        assert!(cart.validate().is_err());
+    }
+}
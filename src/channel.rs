@@ -0,0 +1,218 @@
+//! Speaker positions of the channels of a [`WaveFormat::Extensible`](crate::WaveFormat) file.
+
+use binrw::binrw;
+use core::ops::{BitAnd, BitOr, BitOrAssign};
+
+/// A speaker position, with the bit it occupies in a [`ChannelMask`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum Speaker {
+    FrontLeft = 0x1,
+    FrontRight = 0x2,
+    FrontCenter = 0x4,
+    LowFrequency = 0x8,
+    BackLeft = 0x10,
+    BackRight = 0x20,
+    FrontLeftOfCenter = 0x40,
+    FrontRightOfCenter = 0x80,
+    BackCenter = 0x100,
+    SideLeft = 0x200,
+    SideRight = 0x400,
+    TopCenter = 0x800,
+    TopFrontLeft = 0x1000,
+    TopFrontCenter = 0x2000,
+    TopFrontRight = 0x4000,
+    TopBackLeft = 0x8000,
+    TopBackCenter = 0x10000,
+    TopBackRight = 0x20000,
+}
+
+impl Speaker {
+    /// Every speaker position, in the order their channels are interleaved.
+    pub const ALL: [Speaker; 18] = [
+        Speaker::FrontLeft,
+        Speaker::FrontRight,
+        Speaker::FrontCenter,
+        Speaker::LowFrequency,
+        Speaker::BackLeft,
+        Speaker::BackRight,
+        Speaker::FrontLeftOfCenter,
+        Speaker::FrontRightOfCenter,
+        Speaker::BackCenter,
+        Speaker::SideLeft,
+        Speaker::SideRight,
+        Speaker::TopCenter,
+        Speaker::TopFrontLeft,
+        Speaker::TopFrontCenter,
+        Speaker::TopFrontRight,
+        Speaker::TopBackLeft,
+        Speaker::TopBackCenter,
+        Speaker::TopBackRight,
+    ];
+
+    pub fn bit(self) -> u32 {
+        self as u32
+    }
+}
+
+/// The speaker positions present in a file, as stored in `dwChannelMask`.
+///
+/// Channels are interleaved in the order of the bits that are set, so the first channel belongs
+/// to the lowest speaker position. Channels beyond the set bits have no speaker position. Bits
+/// without a [`Speaker`], such as the reserved `SPEAKER_ALL` bit, are kept as is.
+#[binrw]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChannelMask {
+    #[br(little)]
+    bits: u32,
+}
+
+impl ChannelMask {
+    pub const MONO: ChannelMask = ChannelMask::from_bits(0x4);
+    pub const STEREO: ChannelMask = ChannelMask::from_bits(0x3);
+    /// 5.1 with back speakers, FL FR FC LFE BL BR.
+    pub const SURROUND_5_1: ChannelMask = ChannelMask::from_bits(0x3F);
+    /// 7.1 with back and side speakers, FL FR FC LFE BL BR SL SR.
+    pub const SURROUND_7_1: ChannelMask = ChannelMask::from_bits(0x63F);
+    /// 7.1 with four height speakers, TFL TFR TBL TBR.
+    pub const SURROUND_7_1_4: ChannelMask = ChannelMask::from_bits(0x2D63F);
+
+    pub const fn empty() -> ChannelMask {
+        ChannelMask { bits: 0 }
+    }
+
+    pub const fn from_bits(bits: u32) -> ChannelMask {
+        ChannelMask { bits }
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.bits
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(self, speaker: Speaker) -> bool {
+        self.bits & speaker.bit() != 0
+    }
+
+    pub fn insert(&mut self, speaker: Speaker) {
+        self.bits |= speaker.bit();
+    }
+
+    pub fn remove(&mut self, speaker: Speaker) {
+        self.bits &= !speaker.bit();
+    }
+
+    /// The number of speaker positions present.
+    pub fn len(self) -> usize {
+        self.iter().count()
+    }
+
+    /// The speaker positions present, in interleave order.
+    pub fn iter(self) -> impl Iterator<Item = Speaker> {
+        Speaker::ALL
+            .into_iter()
+            .filter(move |speaker| self.contains(*speaker))
+    }
+
+    /// The speaker position of the interleaved channel at `index`.
+    pub fn speaker(self, index: usize) -> Option<Speaker> {
+        self.iter().nth(index)
+    }
+
+    /// The interleaved channel index of a speaker position.
+    pub fn channel(self, speaker: Speaker) -> Option<usize> {
+        self.iter().position(|present| present == speaker)
+    }
+}
+
+impl From<Speaker> for ChannelMask {
+    fn from(speaker: Speaker) -> Self {
+        ChannelMask::from_bits(speaker.bit())
+    }
+}
+
+impl FromIterator<Speaker> for ChannelMask {
+    fn from_iter<I: IntoIterator<Item = Speaker>>(iter: I) -> Self {
+        let mut mask = ChannelMask::empty();
+        for speaker in iter {
+            mask.insert(speaker);
+        }
+        mask
+    }
+}
+
+impl BitOr for ChannelMask {
+    type Output = ChannelMask;
+
+    fn bitor(self, rhs: ChannelMask) -> ChannelMask {
+        ChannelMask::from_bits(self.bits | rhs.bits)
+    }
+}
+
+impl BitOr<Speaker> for ChannelMask {
+    type Output = ChannelMask;
+
+    fn bitor(self, rhs: Speaker) -> ChannelMask {
+        ChannelMask::from_bits(self.bits | rhs.bit())
+    }
+}
+
+impl BitOrAssign<Speaker> for ChannelMask {
+    fn bitor_assign(&mut self, rhs: Speaker) {
+        self.insert(rhs);
+    }
+}
+
+impl BitAnd for ChannelMask {
+    type Output = ChannelMask;
+
+    fn bitand(self, rhs: ChannelMask) -> ChannelMask {
+        ChannelMask::from_bits(self.bits & rhs.bits)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_maps_channels_to_speakers() {
+        let mask = ChannelMask::SURROUND_7_1_4;
+        assert_eq!(mask.len(), 12);
+        assert_eq!(mask.speaker(0), Some(Speaker::FrontLeft));
+        assert_eq!(mask.speaker(3), Some(Speaker::LowFrequency));
+        assert_eq!(mask.speaker(6), Some(Speaker::SideLeft));
+        assert_eq!(mask.speaker(11), Some(Speaker::TopBackRight));
+        assert_eq!(mask.speaker(12), None);
+        assert_eq!(mask.channel(Speaker::TopFrontLeft), Some(8));
+        assert_eq!(mask.channel(Speaker::BackCenter), None);
+    }
+
+    #[test]
+    fn it_combines_speakers() {
+        let mask: ChannelMask = [Speaker::FrontRight, Speaker::FrontLeft]
+            .into_iter()
+            .collect();
+        assert_eq!(mask, ChannelMask::STEREO);
+        assert_eq!(
+            mask | Speaker::FrontCenter
+                | Speaker::LowFrequency
+                | Speaker::BackLeft
+                | Speaker::BackRight,
+            ChannelMask::SURROUND_5_1
+        );
+        assert_eq!(
+            ChannelMask::SURROUND_7_1 & ChannelMask::from_bits(0x8000_0004),
+            ChannelMask::MONO
+        );
+
+        let mut mask = ChannelMask::from_bits(0x8000_0001);
+        mask.remove(Speaker::FrontLeft);
+        assert!(!mask.is_empty());
+        assert_eq!(mask.iter().count(), 0);
+        assert_eq!(mask.bits(), 0x8000_0000);
+    }
+}
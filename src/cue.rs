@@ -0,0 +1,358 @@
+//! The cue chunk, which marks positions in the audio, and the LIST chunk of type adtl that names
+//! and describes them.
+
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::Cow, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+use binrw::{binrw, BinReaderExt};
+
+use crate::{list, text};
+
+/// Positions of interest in the audio, such as markers and the start of regions.
+#[binrw]
+#[brw(magic = b"cue ")]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CueChunk {
+    #[br(little)]
+    pub size: u32,
+    #[br(little)]
+    pub num_cue_points: u32,
+    #[br(count = num_cue_points)]
+    pub cue_points: Vec<CuePoint>,
+}
+
+impl CueChunk {
+    /// Parses a cue chunk, including its id and size. Returns `None` unless the cue points fill
+    /// the chunk exactly.
+    pub(crate) fn parse(chunk: &[u8]) -> Option<CueChunk> {
+        let count = u32::from_le_bytes(chunk.get(8..12)?.try_into().ok()?);
+        if chunk.len() as u64 != 12 + 24 * count as u64 {
+            return None;
+        }
+        crate::parse_whole(chunk, |cursor| cursor.read_le())
+    }
+
+    pub fn get(&self, id: u32) -> Option<&CuePoint> {
+        self.cue_points.iter().find(|cue_point| cue_point.id == id)
+    }
+
+    /// An id that no cue point uses yet, one past the highest id. Once `u32::MAX` is taken, the
+    /// lowest unused id.
+    pub fn next_id(&self) -> u32 {
+        let used = |id: u32| self.cue_points.iter().any(|cue_point| cue_point.id == id);
+        match self.cue_points.iter().map(|cue_point| cue_point.id).max() {
+            Some(id) => id
+                .checked_add(1)
+                .or_else(|| (1..u32::MAX).find(|&id| !used(id)))
+                .unwrap_or(0),
+            None => 1,
+        }
+    }
+
+    /// Updates `size` and `num_cue_points` to match `cue_points`.
+    pub(crate) fn update_size(&mut self) {
+        self.num_cue_points = self.cue_points.len() as u32;
+        self.size = 4 + 24 * self.num_cue_points;
+    }
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CuePoint {
+    /// Unique id, referenced by the associated data list and the playlist.
+    #[br(little)]
+    pub id: u32,
+    /// The sample frame at which the cue point occurs when the file is played back.
+    #[br(little)]
+    pub position: u32,
+    /// The id of the chunk holding the cue point, `data` unless the file has a wave list.
+    pub data_chunk_id: [u8; 4],
+    /// Offset of the chunk holding the cue point within the wave list, 0 without a wave list.
+    #[br(little)]
+    pub chunk_start: u32,
+    /// Offset of the block holding the cue point within the chunk, for compressed formats.
+    #[br(little)]
+    pub block_start: u32,
+    /// The sample frame of the cue point within the block.
+    #[br(little)]
+    pub sample_offset: u32,
+}
+
+impl CuePoint {
+    /// A cue point at `position` sample frames into the data chunk.
+    pub fn new(id: u32, position: u32) -> CuePoint {
+        CuePoint {
+            id,
+            position,
+            data_chunk_id: *b"data",
+            chunk_start: 0,
+            block_start: 0,
+            sample_offset: position,
+        }
+    }
+}
+
+/// Text attached to a cue point, used by both `labl` and `note` entries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub cue_id: u32,
+    /// The text as stored in the file, usually including a NUL terminator.
+    pub data: Vec<u8>,
+}
+
+impl Label {
+    /// The text without its NUL terminator, decoded as Latin-1 if it is not valid UTF-8, as INFO
+    /// text is.
+    pub fn text(&self) -> Cow<'_, str> {
+        text::utf8_or_latin1(&self.data)
+    }
+}
+
+/// Text attached to a section of audio starting at a cue point, which makes it a region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LabeledText {
+    pub cue_id: u32,
+    /// Length of the section in sample frames.
+    pub sample_length: u32,
+    /// What the text is for, such as `rgn ` for regions.
+    pub purpose: [u8; 4],
+    pub country: u16,
+    pub language: u16,
+    pub dialect: u16,
+    pub code_page: u16,
+    /// The text as stored in the file, usually including a NUL terminator.
+    pub data: Vec<u8>,
+}
+
+impl LabeledText {
+    /// The text without its NUL terminator. See [`Label::text`].
+    pub fn text(&self) -> Cow<'_, str> {
+        text::utf8_or_latin1(&self.data)
+    }
+}
+
+/// A single entry of an [`AssociatedDataList`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssociatedData {
+    /// A `labl` entry, naming a cue point.
+    Label(Label),
+    /// A `note` entry, commenting on a cue point.
+    Note(Label),
+    /// An `ltxt` entry.
+    LabeledText(LabeledText),
+    /// Any other entry, such as `file`, kept as is.
+    Other { id: [u8; 4], data: Vec<u8> },
+}
+
+impl AssociatedData {
+    /// The cue point the entry refers to, if it is of a known type.
+    pub fn cue_id(&self) -> Option<u32> {
+        match self {
+            AssociatedData::Label(label) | AssociatedData::Note(label) => Some(label.cue_id),
+            AssociatedData::LabeledText(text) => Some(text.cue_id),
+            AssociatedData::Other { .. } => None,
+        }
+    }
+}
+
+/// A LIST chunk of type adtl, holding labels, notes and text for the points of a [`CueChunk`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AssociatedDataList {
+    pub entries: Vec<AssociatedData>,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+impl AssociatedDataList {
+    /// Parses the sub-chunks of a LIST chunk, following the adtl list type.
+    pub(crate) fn parse(data: &[u8]) -> AssociatedDataList {
+        let entries = list::sub_chunks(data).map(|(id, data)| match &id {
+            b"labl" | b"note" if data.len() >= 4 => {
+                let label = Label {
+                    cue_id: u32_at(data, 0),
+                    data: data[4..].to_vec(),
+                };
+                if &id == b"labl" {
+                    AssociatedData::Label(label)
+                } else {
+                    AssociatedData::Note(label)
+                }
+            }
+            b"ltxt" if data.len() >= 20 => AssociatedData::LabeledText(LabeledText {
+                cue_id: u32_at(data, 0),
+                sample_length: u32_at(data, 4),
+                purpose: [data[8], data[9], data[10], data[11]],
+                country: u16_at(data, 12),
+                language: u16_at(data, 14),
+                dialect: u16_at(data, 16),
+                code_page: u16_at(data, 18),
+                data: data[20..].to_vec(),
+            }),
+            _ => AssociatedData::Other {
+                id,
+                data: data.to_vec(),
+            },
+        });
+        AssociatedDataList {
+            entries: entries.collect(),
+        }
+    }
+
+    /// The body of the LIST chunk, starting with the adtl list type.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut data = b"adtl".to_vec();
+        let mut body = Vec::new();
+        for entry in &self.entries {
+            body.clear();
+            let id = match entry {
+                AssociatedData::Label(label) | AssociatedData::Note(label) => {
+                    body.extend_from_slice(&label.cue_id.to_le_bytes());
+                    body.extend_from_slice(&label.data);
+                    if let AssociatedData::Label(_) = entry {
+                        *b"labl"
+                    } else {
+                        *b"note"
+                    }
+                }
+                AssociatedData::LabeledText(text) => {
+                    body.extend_from_slice(&text.cue_id.to_le_bytes());
+                    body.extend_from_slice(&text.sample_length.to_le_bytes());
+                    body.extend_from_slice(&text.purpose);
+                    for field in [text.country, text.language, text.dialect, text.code_page] {
+                        body.extend_from_slice(&field.to_le_bytes());
+                    }
+                    body.extend_from_slice(&text.data);
+                    *b"ltxt"
+                }
+                AssociatedData::Other { id, data } => {
+                    body.extend_from_slice(data);
+                    *id
+                }
+            };
+            list::push_sub_chunk(&mut data, id, &body);
+        }
+        data
+    }
+
+    /// The text of the `labl` entry of a cue point.
+    pub fn label(&self, cue_id: u32) -> Option<Cow<'_, str>> {
+        self.entries.iter().find_map(|entry| match entry {
+            AssociatedData::Label(label) if label.cue_id == cue_id => Some(label.text()),
+            _ => None,
+        })
+    }
+
+    /// The text of the `note` entry of a cue point.
+    pub fn note(&self, cue_id: u32) -> Option<Cow<'_, str>> {
+        self.entries.iter().find_map(|entry| match entry {
+            AssociatedData::Note(note) if note.cue_id == cue_id => Some(note.text()),
+            _ => None,
+        })
+    }
+
+    /// The `ltxt` entry of a cue point, which holds the length of a region.
+    pub fn labeled_text(&self, cue_id: u32) -> Option<&LabeledText> {
+        self.entries.iter().find_map(|entry| match entry {
+            AssociatedData::LabeledText(text) if text.cue_id == cue_id => Some(text),
+            _ => None,
+        })
+    }
+
+    /// Sets the `labl` text of a cue point, replacing an existing label.
+    pub fn set_label(&mut self, cue_id: u32, text: &str) {
+        let data = list::to_zstr(text);
+        let existing = self.entries.iter_mut().find_map(|entry| match entry {
+            AssociatedData::Label(label) if label.cue_id == cue_id => Some(label),
+            _ => None,
+        });
+        match existing {
+            Some(label) => label.data = data,
+            None => self
+                .entries
+                .push(AssociatedData::Label(Label { cue_id, data })),
+        }
+    }
+
+    /// Removes every entry that refers to a cue point.
+    pub fn remove(&mut self, cue_id: u32) {
+        self.entries.retain(|entry| entry.cue_id() != Some(cue_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_associated_data() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"labl\x07\0\0\0\x01\0\0\0ab\0\0");
+        data.extend_from_slice(b"note\x06\0\0\0\x01\0\0\0c\0");
+        data.extend_from_slice(b"ltxt\x15\0\0\0\x02\0\0\0\x10\0\0\0rgn \0\0\0\0\0\0\0\0\0\0");
+        data.extend_from_slice(b"file\x04\0\0\0\x01\0\0\0");
+        data.extend_from_slice(b"labl\x09\0\0\0\x03\0\0\0Caf\xe9\0\0");
+
+        let list = AssociatedDataList::parse(&data);
+        assert_eq!(list.label(1).as_deref(), Some("ab"));
+        assert_eq!(list.note(1).as_deref(), Some("c"));
+        assert_eq!(list.label(2), None);
+        assert_eq!(list.label(3).as_deref(), Some("Café"));
+        let text = list.labeled_text(2).unwrap();
+        assert_eq!(text.sample_length, 16);
+        assert_eq!(&text.purpose, b"rgn ");
+        assert_eq!(text.text(), "");
+        assert_eq!(list.to_bytes()[4..], data[..]);
+    }
+
+    #[test]
+    fn it_parses_cue_points_that_fit() {
+        let mut chunk = b"cue \x1c\0\0\0\x01\0\0\0\x07\0\0\0".to_vec();
+        chunk.extend_from_slice(&[0; 20]);
+        assert_eq!(CueChunk::parse(&chunk).unwrap().cue_points[0].id, 7);
+        // One cue point but two counted, or room for one but none counted.
+        chunk[8] = 2;
+        assert_eq!(CueChunk::parse(&chunk), None);
+        chunk[8] = 0;
+        assert_eq!(CueChunk::parse(&chunk), None);
+        assert_eq!(CueChunk::parse(&chunk[..8]), None);
+    }
+
+    #[test]
+    fn it_finds_unused_ids() {
+        let mut cue = CueChunk::default();
+        assert_eq!(cue.next_id(), 1);
+        cue.cue_points.push(CuePoint::new(1, 0));
+        cue.cue_points.push(CuePoint::new(7, 0));
+        assert_eq!(cue.next_id(), 8);
+        cue.cue_points.push(CuePoint::new(u32::MAX, 0));
+        assert_eq!(cue.next_id(), 2);
+    }
+
+    #[test]
+    fn it_relabels_and_removes_entries() {
+        let mut list = AssociatedDataList::default();
+        list.set_label(3, "first");
+        list.set_label(3, "second");
+        list.set_label(4, "other");
+        assert_eq!(list.label(3).as_deref(), Some("second"));
+        assert_eq!(list.entries.len(), 2);
+
+        list.remove(3);
+        assert_eq!(list.label(3), None);
+        assert_eq!(list.label(4).as_deref(), Some("other"));
+    }
+}
@@ -0,0 +1,425 @@
+//! The id3 chunk, which holds an ID3v2 tag like the ones desktop players show for MP3 files.
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+/// An ID3v2.3 or ID3v2.4 tag. Unsynchronisation is not supported, and extended headers and
+/// padding are left out when a modified tag is written. A tag that is not modified is written
+/// back as it was read.
+#[derive(Debug, Clone)]
+pub struct Id3Chunk {
+    /// The chunk id, `id3 ` or `ID3 `.
+    pub id: [u8; 4],
+    /// The major version, 3 or 4.
+    pub version: u8,
+    pub revision: u8,
+    pub frames: Vec<Id3Frame>,
+    /// The body of the chunk as read, empty for new tags.
+    raw: Vec<u8>,
+}
+
+impl PartialEq for Id3Chunk {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+            && self.version == other.version
+            && self.revision == other.revision
+            && self.frames == other.frames
+    }
+}
+
+/// A frame of an [`Id3Chunk`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Id3Frame {
+    /// A text frame such as `TIT2` or `TPE1`, other than `TXXX`.
+    Text { id: [u8; 4], text: String },
+    /// A `COMM` frame.
+    Comment {
+        /// ISO-639-2 language code, such as `eng`.
+        language: [u8; 3],
+        description: String,
+        text: String,
+    },
+    /// An `APIC` frame, with the image kept as is.
+    Picture {
+        mime_type: String,
+        /// What the picture shows, such as 3 for the front cover.
+        picture_type: u8,
+        description: String,
+        data: Vec<u8>,
+    },
+    /// Any other frame, with its flags and body kept as is.
+    Other {
+        id: [u8; 4],
+        flags: [u8; 2],
+        data: Vec<u8>,
+    },
+}
+
+impl Id3Frame {
+    pub fn id(&self) -> [u8; 4] {
+        match self {
+            Id3Frame::Text { id, .. } | Id3Frame::Other { id, .. } => *id,
+            Id3Frame::Comment { .. } => *b"COMM",
+            Id3Frame::Picture { .. } => *b"APIC",
+        }
+    }
+
+    fn parse(id: [u8; 4], flags: [u8; 2], data: &[u8]) -> Id3Frame {
+        let typed = match (&id, data.split_first()) {
+            (b"TXXX", _) => None,
+            ([b'T', ..], Some((&encoding, rest))) => Some(Id3Frame::Text {
+                id,
+                text: decode(encoding, rest),
+            }),
+            (b"COMM", Some((&encoding, rest))) if rest.len() >= 3 => {
+                let (description, text) = split_terminated(encoding, &rest[3..]);
+                Some(Id3Frame::Comment {
+                    language: [rest[0], rest[1], rest[2]],
+                    description: decode(encoding, description),
+                    text: decode(encoding, text),
+                })
+            }
+            (b"APIC", Some((&encoding, rest))) => {
+                let (mime_type, rest) = split_terminated(0, rest);
+                rest.split_first().map(|(&picture_type, rest)| {
+                    let (description, data) = split_terminated(encoding, rest);
+                    Id3Frame::Picture {
+                        mime_type: decode(0, mime_type),
+                        picture_type,
+                        description: decode(encoding, description),
+                        data: data.to_vec(),
+                    }
+                })
+            }
+            _ => None,
+        };
+        // Frames with flags may be compressed or encrypted, so only plain frames are decoded.
+        match typed {
+            Some(frame) if flags == [0, 0] => frame,
+            _ => Id3Frame::Other {
+                id,
+                flags,
+                data: data.to_vec(),
+            },
+        }
+    }
+
+    fn to_bytes(&self, version: u8) -> Vec<u8> {
+        let mut data = Vec::new();
+        match self {
+            Id3Frame::Text { text, .. } => {
+                let encoding = encoding(version, &[text.as_str()]);
+                data.push(encoding);
+                encode(encoding, text, &mut data);
+            }
+            Id3Frame::Comment {
+                language,
+                description,
+                text,
+            } => {
+                let encoding = encoding(version, &[description.as_str(), text.as_str()]);
+                data.push(encoding);
+                data.extend_from_slice(language);
+                encode(encoding, description, &mut data);
+                terminate(encoding, &mut data);
+                encode(encoding, text, &mut data);
+            }
+            Id3Frame::Picture {
+                mime_type,
+                picture_type,
+                description,
+                data: picture,
+            } => {
+                let encoding = encoding(version, &[description.as_str()]);
+                data.push(encoding);
+                encode(0, mime_type, &mut data);
+                data.push(0);
+                data.push(*picture_type);
+                encode(encoding, description, &mut data);
+                terminate(encoding, &mut data);
+                data.extend_from_slice(picture);
+            }
+            Id3Frame::Other { data: body, .. } => data.extend_from_slice(body),
+        }
+        data
+    }
+}
+
+impl Id3Chunk {
+    /// An empty ID3v2.4 tag.
+    pub fn new() -> Id3Chunk {
+        Id3Chunk {
+            id: *b"id3 ",
+            version: 4,
+            revision: 0,
+            frames: Vec::new(),
+            raw: Vec::new(),
+        }
+    }
+
+    /// Parses the body of an id3 chunk. Returns `None` for tags other than ID3v2.3 and ID3v2.4,
+    /// and for tags using unsynchronisation.
+    pub(crate) fn parse(id: [u8; 4], data: &[u8]) -> Option<Id3Chunk> {
+        if data.len() < 10 || &data[..3] != b"ID3" {
+            return None;
+        }
+        let (version, revision, flags) = (data[3], data[4], data[5]);
+        if !(3..=4).contains(&version) || flags & 0x80 != 0 {
+            return None;
+        }
+        let size = syncsafe(&data[6..10]) as usize;
+        let mut tag = &data[10..data.len().min(10 + size)];
+        if flags & 0x40 != 0 {
+            // The extended header, whose size includes itself only from ID3v2.4.
+            let size = match (version, tag.get(..4)) {
+                (4, Some(size)) => syncsafe(size) as usize,
+                (_, Some(size)) => {
+                    u32::from_be_bytes([size[0], size[1], size[2], size[3]]) as usize + 4
+                }
+                _ => return None,
+            };
+            tag = tag.get(size..)?;
+        }
+
+        let mut frames = Vec::new();
+        // Frames end at the padding, which starts with a NUL id.
+        while tag.len() >= 10 && tag[0] != 0 {
+            let id = [tag[0], tag[1], tag[2], tag[3]];
+            let size = match version {
+                4 => syncsafe(&tag[4..8]),
+                _ => u32::from_be_bytes([tag[4], tag[5], tag[6], tag[7]]),
+            } as usize;
+            let flags = [tag[8], tag[9]];
+            let body = tag.get(10..10 + size)?;
+            frames.push(Id3Frame::parse(id, flags, body));
+            tag = &tag[10 + size..];
+        }
+
+        Some(Id3Chunk {
+            id,
+            version,
+            revision,
+            frames,
+            raw: data.to_vec(),
+        })
+    }
+
+    /// The body of the chunk, which for a modified tag is a tag without padding.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        if Id3Chunk::parse(self.id, &self.raw).as_ref() == Some(self) {
+            return self.raw.clone();
+        }
+        let mut frames = Vec::new();
+        for frame in &self.frames {
+            let body = frame.to_bytes(self.version);
+            frames.extend_from_slice(&frame.id());
+            let size = match self.version {
+                4 => to_syncsafe(body.len() as u32),
+                _ => (body.len() as u32).to_be_bytes(),
+            };
+            frames.extend_from_slice(&size);
+            match frame {
+                Id3Frame::Other { flags, .. } => frames.extend_from_slice(flags),
+                _ => frames.extend_from_slice(&[0, 0]),
+            }
+            frames.extend_from_slice(&body);
+        }
+
+        let mut data = Vec::with_capacity(10 + frames.len());
+        data.extend_from_slice(b"ID3");
+        data.extend_from_slice(&[self.version, self.revision, 0]);
+        data.extend_from_slice(&to_syncsafe(frames.len() as u32));
+        data.extend_from_slice(&frames);
+        data
+    }
+
+    /// The text of the first text frame with the given id, such as `TIT2` for the title.
+    pub fn text(&self, id: &[u8; 4]) -> Option<&str> {
+        self.frames.iter().find_map(|frame| match frame {
+            Id3Frame::Text { id: frame_id, text } if frame_id == id => Some(text.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Sets the text of a text frame, replacing the first existing frame with the same id.
+    pub fn set_text(&mut self, id: [u8; 4], text: &str) {
+        let frame = Id3Frame::Text {
+            id,
+            text: text.into(),
+        };
+        match self.frames.iter_mut().find(|frame| frame.id() == id) {
+            Some(existing) => *existing = frame,
+            None => self.frames.push(frame),
+        }
+    }
+}
+
+impl Default for Id3Chunk {
+    fn default() -> Self {
+        Id3Chunk::new()
+    }
+}
+
+/// A 28-bit integer stored in the low 7 bits of each byte.
+fn syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 7) | (byte & 0x7F) as u32)
+}
+
+fn to_syncsafe(value: u32) -> [u8; 4] {
+    [
+        (value >> 21) as u8 & 0x7F,
+        (value >> 14) as u8 & 0x7F,
+        (value >> 7) as u8 & 0x7F,
+        value as u8 & 0x7F,
+    ]
+}
+
+/// Splits at the first terminator of `encoding`, which is two NUL bytes for UTF-16.
+fn split_terminated(encoding: u8, data: &[u8]) -> (&[u8], &[u8]) {
+    let end = match encoding {
+        1 | 2 => data
+            .chunks_exact(2)
+            .position(|pair| pair == [0, 0])
+            .map(|index| (index * 2, 2)),
+        _ => data
+            .iter()
+            .position(|&byte| byte == 0)
+            .map(|index| (index, 1)),
+    };
+    match end {
+        Some((index, len)) => (&data[..index], &data[index + len..]),
+        None => (data, &[]),
+    }
+}
+
+/// Decodes text of an encoding: ISO-8859-1, UTF-16 with a byte order mark, UTF-16BE or UTF-8.
+/// Trailing terminators are dropped.
+fn decode(encoding: u8, data: &[u8]) -> String {
+    let text: String = match encoding {
+        1 | 2 => {
+            let (little_endian, data) = match data {
+                [0xFF, 0xFE, rest @ ..] => (true, rest),
+                [0xFE, 0xFF, rest @ ..] => (false, rest),
+                _ => (encoding == 1, data),
+            };
+            let units = data.chunks_exact(2).map(|pair| {
+                if little_endian {
+                    u16::from_le_bytes([pair[0], pair[1]])
+                } else {
+                    u16::from_be_bytes([pair[0], pair[1]])
+                }
+            });
+            char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect()
+        }
+        3 => String::from_utf8_lossy(data).into(),
+        _ => data.iter().map(|&byte| byte as char).collect(),
+    };
+    text.trim_end_matches('\0').into()
+}
+
+/// The encoding text is written in: ISO-8859-1 where possible, else UTF-8 for ID3v2.4 and UTF-16
+/// for ID3v2.3, which has no UTF-8.
+fn encoding(version: u8, texts: &[&str]) -> u8 {
+    if texts
+        .iter()
+        .all(|text| text.chars().all(|c| c as u32 <= 0xFF))
+    {
+        0
+    } else if version >= 4 {
+        3
+    } else {
+        1
+    }
+}
+
+fn encode(encoding: u8, text: &str, out: &mut Vec<u8>) {
+    match encoding {
+        0 => out.extend(text.chars().map(|c| c as u8)),
+        1 => {
+            out.extend_from_slice(&[0xFF, 0xFE]);
+            for unit in text.encode_utf16() {
+                out.extend_from_slice(&unit.to_le_bytes());
+            }
+        }
+        _ => out.extend_from_slice(text.as_bytes()),
+    }
+}
+
+fn terminate(encoding: u8, out: &mut Vec<u8>) {
+    match encoding {
+        1 | 2 => out.extend_from_slice(&[0, 0]),
+        _ => out.push(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_round_trips_frames() {
+        for version in [3, 4] {
+            let mut tag = Id3Chunk {
+                version,
+                ..Id3Chunk::new()
+            };
+            tag.set_text(*b"TIT2", "Café");
+            tag.set_text(*b"TPE1", "Ünïcødé ☃");
+            tag.frames.push(Id3Frame::Comment {
+                language: *b"eng",
+                description: String::new(),
+                text: "Recorded live".into(),
+            });
+            tag.frames.push(Id3Frame::Picture {
+                mime_type: "image/png".into(),
+                picture_type: 3,
+                description: "Cover ☃".into(),
+                data: [0x89, b'P', b'N', b'G', 0, 0].to_vec(),
+            });
+            tag.frames.push(Id3Frame::Other {
+                id: *b"PRIV",
+                flags: [0, 0],
+                data: [1, 2, 3].to_vec(),
+            });
+
+            let data = tag.to_bytes();
+            let parsed = Id3Chunk::parse(*b"id3 ", &data).unwrap();
+            assert_eq!(parsed, tag);
+            assert_eq!(parsed.text(b"TPE1"), Some("Ünïcødé ☃"));
+        }
+    }
+
+    #[test]
+    fn it_reads_utf16_text() {
+        let mut data = b"ID3\x03\x00\x00\x00\x00\x00\x15TIT2\x00\x00\x00\x09\x00\x00\x01".to_vec();
+        data.extend_from_slice(&[0xFE, 0xFF, 0, b'H', 0, b'i', 0, 0]);
+        data.extend_from_slice(&[0; 2]);
+        let tag = Id3Chunk::parse(*b"ID3 ", &data).unwrap();
+        assert_eq!(tag.text(b"TIT2"), Some("Hi"));
+        assert_eq!(tag.frames.len(), 1);
+
+        assert_eq!(tag.to_bytes(), data);
+
+        data[3] = 2;
+        assert!(Id3Chunk::parse(*b"ID3 ", &data).is_none());
+    }
+
+    #[test]
+    fn it_writes_back_unmodified_tags() {
+        // An ID3v2.3 extended header without CRC, followed by padding.
+        let mut data = b"ID3\x03\x00\x40\x00\x00\x00\x20".to_vec();
+        data.extend_from_slice(b"\x00\x00\x00\x06\x00\x00\x00\x00\x00\x00");
+        data.extend_from_slice(b"TIT2\x00\x00\x00\x03\x00\x00\x00Hi");
+        data.resize(10 + 0x20, 0);
+        let mut tag = Id3Chunk::parse(*b"id3 ", &data).unwrap();
+        assert_eq!(tag.text(b"TIT2"), Some("Hi"));
+        assert_eq!(tag.to_bytes(), data);
+
+        tag.set_text(*b"TIT2", "Ho");
+        assert_eq!(tag.to_bytes().len(), 10 + 13);
+    }
+}
@@ -0,0 +1,226 @@
+//! The LIST chunk of type INFO, which holds text metadata such as the title and artist.
+
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::Cow, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+use crate::{list, text};
+
+/// A well-known INFO tag. Tags without a variant are available through [`InfoTag::Other`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InfoTag {
+    /// IARL, where the subject of the file is archived.
+    ArchivalLocation,
+    /// IART, the artist of the original subject of the file.
+    Artist,
+    /// ICMS, who commissioned the subject of the file.
+    Commissioned,
+    /// ICMT, general comments.
+    Comment,
+    /// ICOP, copyright information.
+    Copyright,
+    /// ICRD, the date the subject of the file was created, preferably as `YYYY-MM-DD`.
+    CreationDate,
+    /// IENG, the engineer who worked on the file.
+    Engineer,
+    /// IGNR, the genre.
+    Genre,
+    /// IKEY, keywords separated by semicolons.
+    Keywords,
+    /// IMED, the medium of the original subject, e.g. "record".
+    Medium,
+    /// INAM, the title.
+    Title,
+    /// IPRD, the product, such as the album, the file was intended for.
+    Product,
+    /// ISBJ, the contents of the file.
+    Subject,
+    /// ISFT, the software package used to create the file.
+    Software,
+    /// ISRC, the person or organization that supplied the original subject.
+    Source,
+    /// ISRF, the original form of the material, e.g. "vinyl".
+    SourceForm,
+    /// ITCH, the technician who digitized the subject.
+    Technician,
+    Other([u8; 4]),
+}
+
+impl InfoTag {
+    pub fn from_id(id: [u8; 4]) -> InfoTag {
+        match &id {
+            b"IARL" => InfoTag::ArchivalLocation,
+            b"IART" => InfoTag::Artist,
+            b"ICMS" => InfoTag::Commissioned,
+            b"ICMT" => InfoTag::Comment,
+            b"ICOP" => InfoTag::Copyright,
+            b"ICRD" => InfoTag::CreationDate,
+            b"IENG" => InfoTag::Engineer,
+            b"IGNR" => InfoTag::Genre,
+            b"IKEY" => InfoTag::Keywords,
+            b"IMED" => InfoTag::Medium,
+            b"INAM" => InfoTag::Title,
+            b"IPRD" => InfoTag::Product,
+            b"ISBJ" => InfoTag::Subject,
+            b"ISFT" => InfoTag::Software,
+            b"ISRC" => InfoTag::Source,
+            b"ISRF" => InfoTag::SourceForm,
+            b"ITCH" => InfoTag::Technician,
+            _ => InfoTag::Other(id),
+        }
+    }
+
+    pub fn id(self) -> [u8; 4] {
+        *match self {
+            InfoTag::ArchivalLocation => b"IARL",
+            InfoTag::Artist => b"IART",
+            InfoTag::Commissioned => b"ICMS",
+            InfoTag::Comment => b"ICMT",
+            InfoTag::Copyright => b"ICOP",
+            InfoTag::CreationDate => b"ICRD",
+            InfoTag::Engineer => b"IENG",
+            InfoTag::Genre => b"IGNR",
+            InfoTag::Keywords => b"IKEY",
+            InfoTag::Medium => b"IMED",
+            InfoTag::Title => b"INAM",
+            InfoTag::Product => b"IPRD",
+            InfoTag::Subject => b"ISBJ",
+            InfoTag::Software => b"ISFT",
+            InfoTag::Source => b"ISRC",
+            InfoTag::SourceForm => b"ISRF",
+            InfoTag::Technician => b"ITCH",
+            InfoTag::Other(id) => return id,
+        }
+    }
+}
+
+/// A single tag of an [`InfoChunk`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct InfoEntry {
+    pub id: [u8; 4],
+    /// The text as stored in the file, usually including a NUL terminator.
+    pub data: Vec<u8>,
+}
+
+impl InfoEntry {
+    /// The text without its NUL terminator. Text that is not valid UTF-8 is decoded as Latin-1,
+    /// which most writers of INFO tags use.
+    pub fn text(&self) -> Cow<'_, str> {
+        text::utf8_or_latin1(&self.data)
+    }
+}
+
+/// A LIST chunk of type INFO, holding text tags in the order they appear in the file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct InfoChunk {
+    pub entries: Vec<InfoEntry>,
+    /// Bytes after the last entry that are too short to form one, kept as is.
+    pub trailing: Vec<u8>,
+}
+
+impl InfoChunk {
+    /// Parses the sub-chunks of a LIST chunk, following the INFO list type.
+    pub(crate) fn parse(data: &[u8]) -> InfoChunk {
+        let mut sub_chunks = list::sub_chunks(data);
+        let entries = sub_chunks
+            .by_ref()
+            .map(|(id, data)| InfoEntry {
+                id,
+                data: data.to_vec(),
+            })
+            .collect();
+        InfoChunk {
+            entries,
+            trailing: sub_chunks.rest().to_vec(),
+        }
+    }
+
+    /// The body of the LIST chunk, starting with the INFO list type.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut data = b"INFO".to_vec();
+        for entry in &self.entries {
+            list::push_sub_chunk(&mut data, entry.id, &entry.data);
+        }
+        data.extend_from_slice(&self.trailing);
+        data
+    }
+
+    pub fn get(&self, tag: InfoTag) -> Option<Cow<'_, str>> {
+        let id = tag.id();
+        Some(self.entries.iter().find(|entry| entry.id == id)?.text())
+    }
+
+    /// Sets the text of a tag, replacing the first existing entry with the same id.
+    pub fn set(&mut self, tag: InfoTag, value: &str) {
+        let data = list::to_zstr(value);
+        let id = tag.id();
+        match self.entries.iter_mut().find(|entry| entry.id == id) {
+            Some(entry) => entry.data = data,
+            None => self.entries.push(InfoEntry { id, data }),
+        }
+    }
+
+    /// Removes every entry of a tag.
+    pub fn remove(&mut self, tag: InfoTag) {
+        let id = tag.id();
+        self.entries.retain(|entry| entry.id != id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_terminators_and_pad_bytes() {
+        let mut data = Vec::new();
+        // Odd length with a pad byte, even length without a terminator, and an odd length
+        // whose pad byte was left out.
+        data.extend_from_slice(b"INAM\x03\0\0\0Hi\0\0");
+        data.extend_from_slice(b"IART\x04\0\0\0Band");
+        data.extend_from_slice(b"ICMT\x01\0\0\0xISFT\x02\0\0\0ok");
+
+        let info = InfoChunk::parse(&data);
+        assert_eq!(info.get(InfoTag::Title).as_deref(), Some("Hi"));
+        assert_eq!(info.get(InfoTag::Artist).as_deref(), Some("Band"));
+        assert_eq!(info.get(InfoTag::Comment).as_deref(), Some("x"));
+        assert_eq!(info.get(InfoTag::Software).as_deref(), Some("ok"));
+        assert_eq!(info.get(InfoTag::Genre).as_deref(), None);
+        assert!(info.trailing.is_empty());
+    }
+
+    #[test]
+    fn it_keeps_trailing_bytes() {
+        let data = b"INAM\x03\0\0\0Hi\0\0\x01\x02\x03";
+        let info = InfoChunk::parse(data);
+        assert_eq!(info.entries.len(), 1);
+        assert_eq!(info.trailing, [1, 2, 3]);
+        assert_eq!(info.to_bytes()[4..], data[..]);
+    }
+
+    #[test]
+    fn it_reads_latin1_text() {
+        let info = InfoChunk::parse(b"INAM\x06\0\0\0Caf\xe9!\0IART\x06\0\0\0Caf\xc3\xa9\0");
+        assert_eq!(info.get(InfoTag::Title).as_deref(), Some("Café!"));
+        assert_eq!(info.get(InfoTag::Artist).as_deref(), Some("Café"));
+    }
+
+    #[test]
+    fn it_writes_terminated_padded_entries() {
+        let mut info = InfoChunk::default();
+        info.set(InfoTag::Title, "Take");
+        info.set(InfoTag::Other(*b"ITRK"), "1");
+        info.set(InfoTag::Title, "Take 2");
+
+        assert_eq!(
+            info.to_bytes(),
+            b"INFOINAM\x07\0\0\0Take 2\0\0ITRK\x02\0\0\x001\0"
+        );
+        assert_eq!(InfoChunk::parse(&info.to_bytes()[4..]), info);
+
+        info.remove(InfoTag::Title);
+        assert_eq!(info.entries.len(), 1);
+    }
+}
@@ -0,0 +1,422 @@
+//! The iXML chunk, which location recorders use for scene, take, track and timecode metadata.
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec::Vec};
+
+use core::ops::Range;
+
+/// The iXML document of a file, kept as text so that elements without accessors survive
+/// editing unchanged.
+///
+/// Paths of elements start below the `BWFXML` root, e.g. `["SPEED", "TIMECODE_RATE"]`. Only
+/// simple documents are understood: elements are matched by name, ignoring attributes, and
+/// elements are assumed not to contain elements of the same name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IxmlChunk {
+    pub xml: String,
+    /// Number of NUL bytes after the document, which recorders leave so that it can be edited
+    /// in place.
+    pub padding: usize,
+}
+
+/// A `TRACK` of the `TRACK_LIST`, describing one channel of the audio.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IxmlTrack {
+    /// The channel of the original recording, numbered from 1.
+    pub channel_index: u16,
+    /// The channel of this file, numbered from 1.
+    pub interleave_index: u16,
+    pub name: String,
+    /// What the track is used for, such as `M-MID_SIDE`.
+    pub function: String,
+}
+
+impl Default for IxmlChunk {
+    fn default() -> Self {
+        IxmlChunk {
+            xml: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<BWFXML>\n</BWFXML>\n"),
+            padding: 0,
+        }
+    }
+}
+
+impl IxmlChunk {
+    /// Parses the body of an iXML chunk, which may be padded with NUL bytes. Returns `None` if
+    /// it is not valid UTF-8.
+    pub(crate) fn parse(data: &[u8]) -> Option<IxmlChunk> {
+        let end = data
+            .iter()
+            .rposition(|&byte| byte != 0)
+            .map_or(0, |last| last + 1);
+        let xml = core::str::from_utf8(&data[..end]).ok()?;
+        Some(IxmlChunk {
+            xml: xml.into(),
+            padding: data.len() - end,
+        })
+    }
+
+    /// The body of the chunk, the document followed by its padding.
+    pub(crate) fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.xml.len() + self.padding);
+        data.extend_from_slice(self.xml.as_bytes());
+        data.resize(self.xml.len() + self.padding, 0);
+        data
+    }
+
+    /// The text of the element at `path`, trimmed and unescaped.
+    pub fn get(&self, path: &[&str]) -> Option<String> {
+        let element = self.find(path)?;
+        Some(unescape(self.xml[element.inner].trim()))
+    }
+
+    /// Sets the text of the element at `path`, adding it and any missing parents.
+    pub fn set(&mut self, path: &[&str], value: &str) {
+        self.set_content(path, &escape(value));
+    }
+
+    pub fn project(&self) -> Option<String> {
+        self.get(&["PROJECT"])
+    }
+
+    pub fn set_project(&mut self, project: &str) {
+        self.set(&["PROJECT"], project);
+    }
+
+    pub fn scene(&self) -> Option<String> {
+        self.get(&["SCENE"])
+    }
+
+    pub fn set_scene(&mut self, scene: &str) {
+        self.set(&["SCENE"], scene);
+    }
+
+    pub fn take(&self) -> Option<String> {
+        self.get(&["TAKE"])
+    }
+
+    pub fn set_take(&mut self, take: &str) {
+        self.set(&["TAKE"], take);
+    }
+
+    /// The timecode rate in frames per second as a fraction, such as `30000/1001`.
+    pub fn timecode_rate(&self) -> Option<(u32, u32)> {
+        let rate = self.get(&["SPEED", "TIMECODE_RATE"])?;
+        let (numerator, denominator) = rate.split_once('/')?;
+        Some((
+            numerator.trim().parse().ok()?,
+            denominator.trim().parse().ok()?,
+        ))
+    }
+
+    pub fn set_timecode_rate(&mut self, numerator: u32, denominator: u32) {
+        self.set(
+            &["SPEED", "TIMECODE_RATE"],
+            &format!("{numerator}/{denominator}"),
+        );
+    }
+
+    /// The tracks of the `TRACK_LIST`, in the order they appear.
+    pub fn tracks(&self) -> Vec<IxmlTrack> {
+        let list = match self.find(&["TRACK_LIST"]) {
+            Some(list) => list,
+            None => return Vec::new(),
+        };
+        children(&self.xml, list.inner)
+            .filter(|(name, _)| *name == "TRACK")
+            .map(|(_, track)| {
+                let field = |name: &str| {
+                    children(&self.xml, track.inner.clone())
+                        .find(|(child, _)| *child == name)
+                        .map(|(_, element)| unescape(self.xml[element.inner].trim()))
+                        .unwrap_or_default()
+                };
+                IxmlTrack {
+                    channel_index: field("CHANNEL_INDEX").parse().unwrap_or(0),
+                    interleave_index: field("INTERLEAVE_INDEX").parse().unwrap_or(0),
+                    name: field("NAME"),
+                    function: field("FUNCTION"),
+                }
+            })
+            .collect()
+    }
+
+    /// Replaces the `TRACK_LIST`, along with its `TRACK_COUNT`.
+    pub fn set_tracks(&mut self, tracks: &[IxmlTrack]) {
+        let mut content = format!("\n<TRACK_COUNT>{}</TRACK_COUNT>\n", tracks.len());
+        for track in tracks {
+            content += &format!(
+                "<TRACK>\n<CHANNEL_INDEX>{}</CHANNEL_INDEX>\n<INTERLEAVE_INDEX>{}</INTERLEAVE_INDEX>\n\
+                 <NAME>{}</NAME>\n<FUNCTION>{}</FUNCTION>\n</TRACK>\n",
+                track.channel_index,
+                track.interleave_index,
+                escape(&track.name),
+                escape(&track.function),
+            );
+        }
+        self.set_content(&["TRACK_LIST"], &content);
+    }
+
+    /// The root element, added if there is none.
+    fn root(&mut self) -> Element {
+        if let Some(root) = self.find(&[]) {
+            return root;
+        }
+        self.xml += "<BWFXML>\n</BWFXML>\n";
+        self.find(&[]).unwrap()
+    }
+
+    fn find(&self, path: &[&str]) -> Option<Element> {
+        let mut element = children(&self.xml, 0..self.xml.len())
+            .find(|(name, _)| *name == "BWFXML")?
+            .1;
+        for name in path {
+            element = children(&self.xml, element.inner)
+                .find(|(child, _)| child == name)?
+                .1;
+        }
+        Some(element)
+    }
+
+    /// Replaces the content of the element at `path` with `content`, which is already escaped.
+    fn set_content(&mut self, path: &[&str], content: &str) {
+        let mut parent = self.root();
+        for (depth, name) in path.iter().enumerate() {
+            let child = children(&self.xml, parent.inner.clone())
+                .find(|(child, _)| child == name)
+                .map(|(_, element)| element);
+            match child {
+                Some(child) => parent = child,
+                None => {
+                    // Add the missing elements just before the end of the last one found.
+                    let mut added = String::from(content);
+                    for name in path[depth..].iter().rev() {
+                        added = format!("<{name}>{added}</{name}>");
+                    }
+                    added.push('\n');
+                    self.xml.insert_str(parent.inner.end, &added);
+                    return;
+                }
+            }
+        }
+        let name = path.last().copied().unwrap_or("BWFXML");
+        self.xml
+            .replace_range(parent.outer, &format!("<{name}>{content}</{name}>"));
+    }
+}
+
+/// Byte ranges of an element, including and excluding its tags.
+#[derive(Debug, Clone)]
+struct Element {
+    outer: Range<usize>,
+    inner: Range<usize>,
+}
+
+/// Iterator over the name and ranges of the elements directly within `xml[range]`.
+fn children(xml: &str, range: Range<usize>) -> impl Iterator<Item = (&str, Element)> {
+    let mut position = range.start;
+    core::iter::from_fn(move || {
+        let (name, element) = next_element(xml, position, range.end)?;
+        position = element.outer.end;
+        Some((name, element))
+    })
+}
+
+/// The first element starting at or after `from`, skipping text, comments and declarations.
+fn next_element(xml: &str, mut from: usize, end: usize) -> Option<(&str, Element)> {
+    loop {
+        let start = from + xml[from..end].find('<')?;
+        let rest = &xml[start..end];
+        if rest.starts_with("</") {
+            return None;
+        }
+        let skip_to = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<![CDATA[") {
+            Some("]]>")
+        } else if rest.starts_with("<?") || rest.starts_with("<!") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(terminator) = skip_to {
+            from = start + rest.find(terminator)? + terminator.len();
+            continue;
+        }
+
+        let name_len = rest[1..]
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .unwrap_or(rest.len() - 1);
+        let name = &rest[1..1 + name_len];
+        let tag_end = start + rest.find('>')?;
+        if xml[..tag_end].ends_with('/') {
+            return Some((
+                name,
+                Element {
+                    outer: start..tag_end + 1,
+                    inner: tag_end + 1..tag_end + 1,
+                },
+            ));
+        }
+        let close = format!("</{name}>");
+        let close_start = tag_end + 1 + xml[tag_end + 1..end].find(&close)?;
+        return Some((
+            name,
+            Element {
+                outer: start..close_start + close.len(),
+                inner: tag_end + 1..close_start,
+            },
+        ));
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped += "&amp;",
+            '<' => escaped += "&lt;",
+            '>' => escaped += "&gt;",
+            '"' => escaped += "&quot;",
+            '\'' => escaped += "&apos;",
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    const ENTITIES: [(&str, char); 5] = [
+        ("&lt;", '<'),
+        ("&gt;", '>'),
+        ("&quot;", '"'),
+        ("&apos;", '\''),
+        ("&amp;", '&'),
+    ];
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('&') {
+        unescaped += &rest[..index];
+        rest = &rest[index..];
+        match ENTITIES.iter().find(|(entity, _)| rest.starts_with(entity)) {
+            Some((entity, c)) => {
+                unescaped.push(*c);
+                rest = &rest[entity.len()..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    unescaped + rest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOUND_DEVICES: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<BWFXML>
+<!-- Written by a recorder -->
+<IXML_VERSION>1.61</IXML_VERSION>
+<PROJECT>Night &amp; Day</PROJECT>
+<SCENE>12A</SCENE>
+<TAKE>3</TAKE>
+<NOTE/>
+<SPEED>
+<NOTE>Camera speed</NOTE>
+<TIMECODE_RATE>24000/1001</TIMECODE_RATE>
+<TIMECODE_FLAG>NDF</TIMECODE_FLAG>
+</SPEED>
+<TRACK_LIST>
+<TRACK_COUNT>2</TRACK_COUNT>
+<TRACK>
+<CHANNEL_INDEX>1</CHANNEL_INDEX>
+<INTERLEAVE_INDEX>1</INTERLEAVE_INDEX>
+<NAME>Boom</NAME>
+</TRACK>
+<TRACK>
+<CHANNEL_INDEX>3</CHANNEL_INDEX>
+<INTERLEAVE_INDEX>2</INTERLEAVE_INDEX>
+<NAME>Lav &lt;1&gt;</NAME>
+<FUNCTION>LAV</FUNCTION>
+</TRACK>
+</TRACK_LIST>
+</BWFXML>
+";
+
+    #[test]
+    fn it_reads_common_fields() {
+        let data = [SOUND_DEVICES.as_bytes(), &[0, 0]].concat();
+        let ixml = IxmlChunk::parse(&data).unwrap();
+        assert_eq!(ixml.xml, SOUND_DEVICES);
+        assert_eq!(ixml.to_bytes(), data);
+        assert_eq!(ixml.project().as_deref(), Some("Night & Day"));
+        assert_eq!(ixml.scene().as_deref(), Some("12A"));
+        assert_eq!(ixml.take().as_deref(), Some("3"));
+        assert_eq!(ixml.get(&["NOTE"]).as_deref(), Some(""));
+        assert_eq!(ixml.timecode_rate(), Some((24000, 1001)));
+        assert_eq!(
+            ixml.tracks(),
+            [
+                IxmlTrack {
+                    channel_index: 1,
+                    interleave_index: 1,
+                    name: "Boom".into(),
+                    function: "".into(),
+                },
+                IxmlTrack {
+                    channel_index: 3,
+                    interleave_index: 2,
+                    name: "Lav <1>".into(),
+                    function: "LAV".into(),
+                },
+            ]
+        );
+        assert_eq!(IxmlChunk::parse(&[0xFF, 0xFE]), None);
+    }
+
+    #[test]
+    fn it_edits_fields() {
+        let mut ixml = IxmlChunk {
+            xml: SOUND_DEVICES.into(),
+            padding: 0,
+        };
+        ixml.set_scene("14");
+        ixml.set_timecode_rate(25, 1);
+        ixml.set(&["NOTE"], "Wind");
+        ixml.set(&["LOCATION", "LOCATION_NAME"], "Studio <B>");
+        let tracks = [IxmlTrack {
+            channel_index: 1,
+            interleave_index: 1,
+            name: "Mix".into(),
+            function: "MIX".into(),
+        }];
+        ixml.set_tracks(&tracks);
+
+        assert_eq!(ixml.scene().as_deref(), Some("14"));
+        assert_eq!(ixml.timecode_rate(), Some((25, 1)));
+        assert_eq!(ixml.get(&["NOTE"]).as_deref(), Some("Wind"));
+        assert_eq!(
+            ixml.get(&["SPEED", "NOTE"]).as_deref(),
+            Some("Camera speed")
+        );
+        assert_eq!(
+            ixml.get(&["LOCATION", "LOCATION_NAME"]).as_deref(),
+            Some("Studio <B>")
+        );
+        assert!(ixml.xml.contains(
+            "<LOCATION><LOCATION_NAME>Studio &lt;B&gt;</LOCATION_NAME></LOCATION>\n</BWFXML>"
+        ));
+        assert_eq!(
+            ixml.get(&["TRACK_LIST", "TRACK_COUNT"]).as_deref(),
+            Some("1")
+        );
+        assert_eq!(ixml.tracks(), tracks);
+        assert_eq!(ixml.project().as_deref(), Some("Night & Day"));
+
+        let mut empty = IxmlChunk::default();
+        empty.set_project("Pilot");
+        assert_eq!(empty.project().as_deref(), Some("Pilot"));
+    }
+}
@@ -3,8 +3,8 @@
 //! This library is meant to provide access to all data within a WAV file,
 //! including FACT and PEAK chunks and extensible version of format chunks.
 //!
-//! This library does not provide any methods to convert sound bytes into
-//! samples, though the necessary information to do so is available.
+//! The data chunk is kept as raw bytes, which can be decoded into typed
+//! samples with [`Wave::samples`].
 //!
 //! `Waverly` also supports `no_std`, however requires `alloc` -- as this a requirement for `binrw` dependency.
 //!
@@ -24,9 +24,13 @@
 extern crate alloc;
 
 #[cfg(not(feature = "std"))]
-use alloc::vec::Vec;
+use alloc::{borrow::Cow, vec, vec::Vec};
 
-use binrw::{binrw, until_exclusive, BinRead, BinWrite};
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+use binrw::{binrw, BinReaderExt, BinWriterExt};
+use core::iter;
 
 #[cfg(not(feature = "std"))]
 use binrw::io;
@@ -34,12 +38,62 @@ use binrw::io;
 #[cfg(feature = "std")]
 use std::io;
 
+mod acid;
+mod adpcm;
+mod bext;
+mod builder;
+mod bw64;
+mod cart;
+mod channel;
+mod cue;
+mod id3;
+mod info;
+mod ixml;
+mod list;
+mod playlist;
+mod reader;
+mod sample;
+mod sampler;
+mod text;
+mod writer;
+
+pub use acid::{AcidChunk, AcidFlags};
+pub use adpcm::{MsAdpcmCoefficient, MsAdpcmFormat};
+pub use bext::BextChunk;
+pub use builder::WaveBuilder;
+pub use bw64::{AudioId, ChnaChunk};
+pub use cart::{CartChunk, PostTimer};
+pub use channel::{ChannelMask, Speaker};
+pub use cue::{AssociatedData, AssociatedDataList, CueChunk, CuePoint, Label, LabeledText};
+pub use id3::{Id3Chunk, Id3Frame};
+pub use info::{InfoChunk, InfoEntry, InfoTag};
+pub use ixml::{IxmlChunk, IxmlTrack};
+pub use playlist::{PlaylistChunk, Segment};
+pub use reader::{StreamSamples, WaveReader};
+pub use sample::{Sample, Samples};
+pub use sampler::{InstrumentChunk, LoopType, SampleLoop, SamplerChunk};
+pub use writer::WaveWriter;
+
 pub type Result<T> = core::result::Result<T, WaverlyError>;
 
 #[derive(Debug)]
 pub enum WaverlyError {
     IoError(io::Error),
     ParseError(binrw::Error),
+    /// The combination of format and bit depth cannot be converted to samples.
+    UnsupportedFormat {
+        format: WaveFormat,
+        bits_per_sample: u16,
+    },
+    /// A playlist segment refers to a cue point that does not exist.
+    UnknownCuePoint(u32),
+    /// The number of valid bits per sample is zero or does not fit the sample container.
+    InvalidBitDepth {
+        bits_per_sample: u16,
+        valid_bits_per_sample: u16,
+    },
+    /// The file does not follow the format, which [`ParseOptions::strict`] does not allow.
+    Malformed(ParseWarning),
 }
 
 impl From<io::Error> for WaverlyError {
@@ -53,62 +107,180 @@ impl From<binrw::Error> for WaverlyError {
     }
 }
 
-#[binrw]
-#[derive(Debug)]
-struct MyFile {
-    #[br(parse_with = until_exclusive(|byte| byte == &Chunk::Eof))]
-    chunks: Vec<Chunk>,
+/// How [`Wave::from_reader_with_options`] treats files that do not follow the format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseOptions {
+    /// Fail with [`WaverlyError::Malformed`] at the first problem, rather than working around it
+    /// and reporting a [`ParseWarning`]. Off by default, as many files in the wild have wrong
+    /// sizes or missing pad bytes.
+    pub strict: bool,
 }
 
-#[binrw]
-#[derive(Debug, PartialEq)]
-enum Chunk {
-    Riff(RiffChunk),
-    Format(FormatChunk),
-    Fact(FactChunk),
-    Peak(PeakChunk),
-    Data(DataChunk),
-    /// This is a "patch" to help process malformed WAV files that contain extra data where none
-    /// should exist. Such as in the case of a PCM formatted WAV file that contains an extensible
-    /// format data that's empty.
-    #[brw(magic = b"\0")]
-    Empty,
-    /// EOF
-    #[brw(magic = b"")]
-    Eof,
+/// A problem with a file, found `offset` bytes from its start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWarning {
+    pub offset: u64,
+    pub kind: ParseWarningKind,
+}
+
+/// The problems lenient parsing works around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarningKind {
+    /// The size in the RIFF or ds64 header does not match the length of the file. Chunks are read
+    /// up to the end of the file.
+    RiffSize { stored: u64, actual: u64 },
+    /// A chunk runs past the end of the file. The data chunk keeps the bytes that are present,
+    /// while other chunks are dropped.
+    TruncatedChunk {
+        id: [u8; 4],
+        size: u64,
+        available: u64,
+    },
+    /// An odd sized chunk is not followed by a pad byte.
+    MissingPadByte { id: [u8; 4] },
+    /// The extension size of the format chunk does not match the size of the chunk, and is
+    /// replaced by the size the chunk leaves for it.
+    ExtensionSize { stored: u16, actual: u16 },
+    /// A chunk whose body cannot be parsed as its kind, or holds bytes its kind has no room for,
+    /// which is kept as an [`UnknownChunk`].
+    InvalidChunk { id: [u8; 4] },
+    /// A chunk of a kind that was already read, which is kept as an [`UnknownChunk`].
+    DuplicateChunk { id: [u8; 4] },
+    /// Bytes after the last chunk that do not form a chunk, which are ignored.
+    TrailingBytes { len: u64 },
+}
+
+/// Whether `id` can be a chunk id, which is made of printable ASCII.
+fn is_chunk_id(id: &[u8; 4]) -> bool {
+    id.iter().all(|byte| (0x20..=0x7E).contains(byte))
+}
+
+/// Whether a chunk header fits at `offset`, and starts with a chunk id.
+fn chunk_id_at<T: io::Seek + io::Read>(reader: &mut T, offset: u64, end: u64) -> Result<bool> {
+    if offset + 8 > end {
+        return Ok(false);
+    }
+    reader.seek(io::SeekFrom::Start(offset))?;
+    let id: [u8; 4] = reader.read_le()?;
+    Ok(is_chunk_id(&id))
+}
+
+/// Reads the body of the chunk at `start`.
+fn read_body<T: io::Seek + io::Read>(reader: &mut T, start: u64, size: u64) -> Result<Vec<u8>> {
+    reader.seek(io::SeekFrom::Start(start + 8))?;
+    let mut data = vec![0; size as usize];
+    reader.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Parses a chunk, including its id and size, with `read`. Returns `None` if `read` fails or
+/// leaves bytes of the chunk unread, which writing the chunk back would drop.
+pub(crate) fn parse_whole<T>(
+    chunk: &[u8],
+    read: impl FnOnce(&mut io::Cursor<&[u8]>) -> binrw::BinResult<T>,
+) -> Option<T> {
+    let mut cursor = io::Cursor::new(chunk);
+    let parsed = read(&mut cursor).ok()?;
+    (cursor.position() == chunk.len() as u64).then_some(parsed)
 }
 
+/// The id and size that precede the body of every chunk.
 #[binrw]
-#[brw(repr = u16)]
 #[derive(Debug, PartialEq)]
+struct ChunkHeader {
+    id: [u8; 4],
+    #[br(little)]
+    size: u32,
+}
+
+/// The format tag of a format chunk. Tags without a variant are available through
+/// [`WaveFormat::Other`].
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum WaveFormat {
-    Pcm = 0x01,
-    IeeeFloat = 0x03,
+    Pcm,
+    /// Microsoft ADPCM
+    MsAdpcm,
+    IeeeFloat,
     /// 8-bit ITU-T G.711 A-law
-    Alaw = 0x06,
+    Alaw,
     /// 8-bit ITU-T G.711 µ-law
-    Mulaw = 0x07,
-    Extensible = 0x08,
+    Mulaw,
+    /// IMA/DVI ADPCM
+    ImaAdpcm,
+    /// ITU-T G.723 ADPCM
+    G723Adpcm,
+    /// GSM 6.10
+    Gsm610,
+    /// ITU-T G.721 ADPCM
+    G721Adpcm,
+    /// MPEG-1 audio layer 1 or 2
+    Mpeg,
+    /// MPEG-1 audio layer 3
+    MpegLayer3,
+    /// ITU-T G.726 ADPCM
+    G726Adpcm,
+    /// Dolby AC-3 over S/PDIF
+    DolbyAc3Spdif,
+    /// Windows Media Audio
+    WindowsMediaAudio,
+    /// WAVE_FORMAT_EXTENSIBLE, where the actual format is given by
+    /// [`ExtensibleFormat::sub_format`].
+    Extensible,
+    Other(u16),
 }
 
-#[binrw]
-#[brw(repr = u16)]
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub enum BitDepth {
-    Eight = 0x08,
-    Sixteen = 0x10,
-    TwentyFour = 0x18,
-    ThirtyTwo = 0x20,
-    SixtyFour = 0x40,
+impl WaveFormat {
+    pub fn from_tag(tag: u16) -> WaveFormat {
+        match tag {
+            0x0001 => WaveFormat::Pcm,
+            0x0002 => WaveFormat::MsAdpcm,
+            0x0003 => WaveFormat::IeeeFloat,
+            0x0006 => WaveFormat::Alaw,
+            0x0007 => WaveFormat::Mulaw,
+            0x0011 => WaveFormat::ImaAdpcm,
+            0x0014 => WaveFormat::G723Adpcm,
+            0x0031 => WaveFormat::Gsm610,
+            0x0040 => WaveFormat::G721Adpcm,
+            0x0050 => WaveFormat::Mpeg,
+            0x0055 => WaveFormat::MpegLayer3,
+            0x0064 => WaveFormat::G726Adpcm,
+            0x0092 => WaveFormat::DolbyAc3Spdif,
+            0x0161 => WaveFormat::WindowsMediaAudio,
+            0xFFFE => WaveFormat::Extensible,
+            _ => WaveFormat::Other(tag),
+        }
+    }
+
+    pub fn tag(&self) -> u16 {
+        match self {
+            WaveFormat::Pcm => 0x0001,
+            WaveFormat::MsAdpcm => 0x0002,
+            WaveFormat::IeeeFloat => 0x0003,
+            WaveFormat::Alaw => 0x0006,
+            WaveFormat::Mulaw => 0x0007,
+            WaveFormat::ImaAdpcm => 0x0011,
+            WaveFormat::G723Adpcm => 0x0014,
+            WaveFormat::Gsm610 => 0x0031,
+            WaveFormat::G721Adpcm => 0x0040,
+            WaveFormat::Mpeg => 0x0050,
+            WaveFormat::MpegLayer3 => 0x0055,
+            WaveFormat::G726Adpcm => 0x0064,
+            WaveFormat::DolbyAc3Spdif => 0x0092,
+            WaveFormat::WindowsMediaAudio => 0x0161,
+            WaveFormat::Extensible => 0xFFFE,
+            WaveFormat::Other(tag) => *tag,
+        }
+    }
 }
 
 #[binrw]
-#[brw(magic = b"WAVEfmt ")]
+#[brw(magic = b"fmt ")]
 #[derive(Debug, PartialEq)]
 pub struct FormatChunk {
     #[br(little)]
     pub size: u32,
-    #[br(little)]
+    #[br(little, map = WaveFormat::from_tag)]
+    #[bw(map = WaveFormat::tag)]
     pub audio_format: WaveFormat,
     #[br(little)]
     pub num_channels: u16,
@@ -123,25 +295,190 @@ pub struct FormatChunk {
     /// alignment.
     #[br(little)]
     pub block_align: u16,
+    /// Number of bits of each sample. Samples of PCM formats occupy this number rounded up to
+    /// a whole number of bytes, unless [`ExtensibleFormat::valid_bits_per_sample`] is set, in
+    /// which case this is the size of the container.
     #[br(little)]
-    pub bits_per_sample: BitDepth,
-    #[br(little, if(audio_format == WaveFormat::Pcm))]
+    pub bits_per_sample: u16,
+    /// Size in bytes of the format specific extension that follows, only present when the
+    /// chunk is longer than 16 bytes.
+    #[br(little, if(size >= 18))]
+    pub extension_size: Option<u16>,
+    #[br(little, if(audio_format == WaveFormat::Extensible && extension_size.unwrap_or(0) >= 22))]
     pub extensible: Option<ExtensibleFormat>,
+    /// Format specific bytes that follow, such as the coefficients of MS ADPCM. Kept as is for
+    /// formats that are not interpreted.
+    #[br(count = extra_len(size, extension_size, extensible.is_some()))]
+    pub extra: Vec<u8>,
+}
+
+/// The number of bytes of the format chunk after the extension size and the extensible format.
+fn extra_len(size: u32, extension_size: Option<u16>, extensible: bool) -> u32 {
+    match extension_size {
+        Some(_) => size.saturating_sub(if extensible { 40 } else { 18 }),
+        None => 0,
+    }
+}
+
+impl FormatChunk {
+    /// The format samples are stored in, which for [`WaveFormat::Extensible`] is given by the
+    /// sub format. Stays [`WaveFormat::Extensible`] if the sub format has no equivalent tag.
+    pub fn effective_format(&self) -> WaveFormat {
+        match (&self.audio_format, &self.extensible) {
+            (WaveFormat::Extensible, Some(extensible)) => extensible
+                .sub_format()
+                .wave_format()
+                .unwrap_or(WaveFormat::Extensible),
+            (format, _) => *format,
+        }
+    }
+
+    /// The coefficients and block size of an MS ADPCM format.
+    pub fn ms_adpcm(&self) -> Option<MsAdpcmFormat> {
+        match self.audio_format {
+            WaveFormat::MsAdpcm => MsAdpcmFormat::parse(&self.extra),
+            _ => None,
+        }
+    }
+
+    /// Number of bits each sample occupies in the data chunk.
+    pub fn container_bits(&self) -> u16 {
+        match self.extensible {
+            Some(_) => self.bits_per_sample,
+            None => self.bits_per_sample.div_ceil(8) * 8,
+        }
+    }
+
+    /// Number of bits of each sample that hold audio, stored in the most significant bits of the
+    /// container.
+    pub fn valid_bits(&self) -> u16 {
+        match &self.extensible {
+            Some(extensible) if extensible.valid_bits_per_sample != 0 => {
+                extensible.valid_bits_per_sample
+            }
+            _ => self.bits_per_sample,
+        }
+    }
+
+    /// Updates `size` and `extension_size` to match the extensible format and extra bytes.
+    pub(crate) fn update_size(&mut self) {
+        if self.extension_size.is_some() || self.extensible.is_some() || !self.extra.is_empty() {
+            let extension_size = self.extra.len() + if self.extensible.is_some() { 22 } else { 0 };
+            self.extension_size = Some(extension_size as u16);
+            self.size = 18 + extension_size as u32;
+        }
+    }
+
+    /// Checks that samples have a non-zero number of bits that fit in a whole number of bytes.
+    pub fn validate_bit_depth(&self) -> Result<()> {
+        let container_bits = self.container_bits();
+        let valid_bits = self.valid_bits();
+        if valid_bits == 0 || valid_bits > container_bits || !container_bits.is_multiple_of(8) {
+            return Err(WaverlyError::InvalidBitDepth {
+                bits_per_sample: self.bits_per_sample,
+                valid_bits_per_sample: valid_bits,
+            });
+        }
+        Ok(())
+    }
 }
 
+/// The extension of the format chunk used by [`WaveFormat::Extensible`].
 #[binrw]
 #[derive(Debug, PartialEq)]
 pub struct ExtensibleFormat {
-    #[br(little)]
-    pub size: u16,
+    /// Number of bits of each sample that hold audio, at most `bits_per_sample`.
     #[br(little)]
     pub valid_bits_per_sample: u16,
-    #[br(little)]
-    pub channel_mask: u32,
-    #[br(little)]
+    /// The speaker position of each channel.
+    pub channel_mask: ChannelMask,
     pub sub_format_guid: [u8; 16],
 }
 
+impl ExtensibleFormat {
+    pub fn sub_format(&self) -> SubFormat {
+        SubFormat::from_guid(self.sub_format_guid)
+    }
+
+    pub fn set_sub_format(&mut self, sub_format: SubFormat) {
+        self.sub_format_guid = sub_format.guid();
+    }
+}
+
+/// The format of [`WaveFormat::Extensible`] samples, identified by a GUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubFormat {
+    Pcm,
+    IeeeFloat,
+    Alaw,
+    Mulaw,
+    /// Ambisonic B-format with PCM samples.
+    AmbisonicBFormatPcm,
+    /// Ambisonic B-format with IEEE float samples.
+    AmbisonicBFormatIeeeFloat,
+    Unknown([u8; 16]),
+}
+
+impl SubFormat {
+    /// The GUID of the format tag based sub formats, with the tag in the first two bytes.
+    const BASE_GUID: [u8; 16] = [
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38, 0x9B,
+        0x71,
+    ];
+    /// The GUID of the ambisonic sub formats, with the sample format tag in the first two bytes.
+    const AMBISONIC_GUID: [u8; 16] = [
+        0x00, 0x00, 0x00, 0x00, 0x21, 0x07, 0xD3, 0x11, 0x86, 0x44, 0xC8, 0xC1, 0xCA, 0x00, 0x00,
+        0x00,
+    ];
+
+    pub fn from_guid(guid: [u8; 16]) -> SubFormat {
+        let tag = u16::from_le_bytes([guid[0], guid[1]]);
+        if guid[2..] == SubFormat::BASE_GUID[2..] {
+            match tag {
+                0x01 => return SubFormat::Pcm,
+                0x03 => return SubFormat::IeeeFloat,
+                0x06 => return SubFormat::Alaw,
+                0x07 => return SubFormat::Mulaw,
+                _ => (),
+            }
+        } else if guid[2..] == SubFormat::AMBISONIC_GUID[2..] {
+            match tag {
+                0x01 => return SubFormat::AmbisonicBFormatPcm,
+                0x03 => return SubFormat::AmbisonicBFormatIeeeFloat,
+                _ => (),
+            }
+        }
+        SubFormat::Unknown(guid)
+    }
+
+    pub fn guid(self) -> [u8; 16] {
+        let (tag, mut guid): (u16, _) = match self {
+            SubFormat::Pcm => (0x01, SubFormat::BASE_GUID),
+            SubFormat::IeeeFloat => (0x03, SubFormat::BASE_GUID),
+            SubFormat::Alaw => (0x06, SubFormat::BASE_GUID),
+            SubFormat::Mulaw => (0x07, SubFormat::BASE_GUID),
+            SubFormat::AmbisonicBFormatPcm => (0x01, SubFormat::AMBISONIC_GUID),
+            SubFormat::AmbisonicBFormatIeeeFloat => (0x03, SubFormat::AMBISONIC_GUID),
+            SubFormat::Unknown(guid) => return guid,
+        };
+        guid[..2].copy_from_slice(&tag.to_le_bytes());
+        guid
+    }
+
+    /// The format tag with the same sample layout, if there is one.
+    pub fn wave_format(self) -> Option<WaveFormat> {
+        match self {
+            SubFormat::Pcm | SubFormat::AmbisonicBFormatPcm => Some(WaveFormat::Pcm),
+            SubFormat::IeeeFloat | SubFormat::AmbisonicBFormatIeeeFloat => {
+                Some(WaveFormat::IeeeFloat)
+            }
+            SubFormat::Alaw => Some(WaveFormat::Alaw),
+            SubFormat::Mulaw => Some(WaveFormat::Mulaw),
+            SubFormat::Unknown(_) => None,
+        }
+    }
+}
+
 #[binrw]
 #[brw(magic = b"fact")]
 #[derive(Debug, PartialEq)]
@@ -150,18 +487,29 @@ pub struct FactChunk {
     pub size: u32,
     #[br(little)]
     pub data: u32,
+    /// Bytes after the sample count, which some writers add. Kept as is.
+    #[br(count = size.saturating_sub(4))]
+    pub trailing: Vec<u8>,
 }
 
 #[binrw]
 #[brw(magic = b"data")]
 #[derive(Debug, PartialEq)]
 pub struct DataChunk {
-    #[br(little)]
-    pub size: u32,
+    /// Stored as `0xFFFFFFFF` when it does not fit in 32 bits, with the actual size kept in the
+    /// ds64 chunk of an RF64 file.
+    #[br(little, map = |size: u32| size as u64)]
+    #[bw(map = chunk_size)]
+    pub size: u64,
     #[br(count = size)]
     pub data: Vec<u8>,
 }
 
+/// The 32-bit size field of a chunk, which is `0xFFFFFFFF` for chunks of 4 GiB and over.
+fn chunk_size(size: &u64) -> u32 {
+    u32::try_from(*size).unwrap_or(u32::MAX)
+}
+
 /// Indicates the peak amplitude of the soundfile
 #[binrw]
 #[brw(magic = b"PEAK")]
@@ -178,10 +526,49 @@ pub struct PeakChunk {
     pub timestamp: u32,
     /// PositionPeak for each channel, in the same order as the samples
     /// are interleaved.
-    #[br(count = 2)]
+    #[br(count = size.saturating_sub(8) / 8)]
     pub peaks: Vec<Peak>,
 }
 
+impl PeakChunk {
+    /// Finds the largest absolute sample value of each channel, and the first frame it occurs in,
+    /// dated `timestamp` seconds after the Unix epoch. Fails if the format has no channels.
+    pub fn from_samples(format: &FormatChunk, data: &[u8], timestamp: u32) -> Result<PeakChunk> {
+        let num_channels = format.num_channels as usize;
+        if num_channels == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Channel count must be non-zero.",
+            )
+            .into());
+        }
+        let mut peaks = vec![
+            Peak {
+                value: 0.0,
+                position: 0,
+            };
+            num_channels
+        ];
+
+        let samples: Samples<f32> = Samples::new(format, data)?;
+        for (index, sample) in samples.enumerate() {
+            let peak = &mut peaks[index % num_channels];
+            let value = if sample < 0.0 { -sample } else { sample };
+            if value > peak.value {
+                peak.value = value;
+                peak.position = (index / num_channels) as u32;
+            }
+        }
+
+        Ok(PeakChunk {
+            size: 8 + 8 * num_channels as u32,
+            version: 1,
+            timestamp,
+            peaks,
+        })
+    }
+}
+
 /// Amplitude peak
 #[binrw]
 #[derive(Clone, Debug, PartialEq)]
@@ -195,54 +582,572 @@ pub struct Peak {
     pub position: u32,
 }
 
+/// A chunk without a dedicated type, such as LIST, JUNK or vendor specific chunks. Its body is
+/// kept as is so that it is written back unchanged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnknownChunk {
+    pub id: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+/// Writes a chunk from its id and body, followed by a pad byte if needed.
+fn write_raw_chunk<T: io::Seek + io::Write>(
+    writer: &mut T,
+    id: [u8; 4],
+    data: &[u8],
+) -> Result<()> {
+    writer.write_le(&ChunkHeader {
+        id,
+        size: chunk_size(&(data.len() as u64)),
+    })?;
+    writer.write_all(data)?;
+    write_pad_byte(writer, data.len())
+}
+
+/// The outermost chunk of a file, which decides how sizes of 4 GiB and over are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Container {
+    /// A RIFF chunk, limited to 4 GiB. Written as RF64 instead when the file would exceed that.
+    #[default]
+    Riff,
+    /// An RF64 chunk, with 64-bit sizes kept in a ds64 chunk.
+    Rf64,
+    /// An ITU-R BS.2088 BW64 chunk, which like RF64 keeps 64-bit sizes in a ds64 chunk. Its ADM
+    /// metadata is kept in the axml and chna chunks.
+    Bw64,
+}
+
+impl Container {
+    fn id(self) -> [u8; 4] {
+        match self {
+            Container::Riff => *b"RIFF",
+            Container::Rf64 => *b"RF64",
+            Container::Bw64 => *b"BW64",
+        }
+    }
+
+    /// Whether sizes are kept in a ds64 chunk.
+    fn has_ds64(self) -> bool {
+        self != Container::Riff
+    }
+}
+
+/// The 64-bit sizes of an RF64 file. The RIFF and data chunks, and any other chunk listed in
+/// the table, store `0xFFFFFFFF` as their 32-bit size. Recomputed by [`Wave::write`].
 #[binrw]
-#[brw(magic = b"RIFF")]
-#[derive(Debug, PartialEq)]
-struct RiffChunk {
+#[brw(magic = b"ds64")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ds64Chunk {
     #[br(little)]
-    size: u32,
+    pub size: u32,
+    #[br(little)]
+    pub riff_size: u64,
+    #[br(little)]
+    pub data_size: u64,
+    /// The number of sample frames, which the FACT chunk cannot hold past 32 bits.
+    #[br(little)]
+    pub sample_count: u64,
+    #[br(little)]
+    pub table_length: u32,
+    /// The sizes of chunks other than the data chunk that do not fit in 32 bits.
+    #[br(count = table_length)]
+    pub table: Vec<ChunkSize>,
+}
+
+impl Ds64Chunk {
+    /// A ds64 chunk whose RIFF size is filled in once the file is written.
+    pub(crate) fn new(data_size: u64, sample_count: u64, table: Vec<ChunkSize>) -> Ds64Chunk {
+        Ds64Chunk {
+            size: 28 + 12 * table.len() as u32,
+            riff_size: 0,
+            data_size,
+            sample_count,
+            table_length: table.len() as u32,
+            table,
+        }
+    }
+
+    /// The 64-bit size of the first chunk with the given id.
+    pub fn chunk_size(&self, id: &[u8; 4]) -> Option<u64> {
+        if id == b"data" {
+            return Some(self.data_size);
+        }
+        self.table
+            .iter()
+            .find(|entry| &entry.id == id)
+            .map(|entry| entry.size)
+    }
 }
 
+/// An entry of the table of a [`Ds64Chunk`].
 #[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkSize {
+    pub id: [u8; 4],
+    #[br(little)]
+    pub size: u64,
+}
+
+// `stream_position` is not part of the `no_std` io traits.
+#[allow(clippy::seek_from_current)]
+fn stream_position<T: io::Seek>(stream: &mut T) -> Result<u64> {
+    Ok(stream.seek(io::SeekFrom::Current(0))?)
+}
+
+/// Chunk bodies are word aligned, so odd sized chunks are followed by a pad byte.
+fn write_pad_byte<T: io::Write>(writer: &mut T, size: usize) -> Result<()> {
+    if size % 2 == 1 {
+        writer.write_all(&[0])?;
+    }
+    Ok(())
+}
+
+/// Where [`Wave::write`] places chunks that were not read from a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkOrder {
+    /// The format chunk first and the data chunk last, with every other chunk in between.
+    #[default]
+    DataLast,
+    /// The format, FACT and PEAK chunks, then the data chunk, followed by every other chunk.
+    DataFirst,
+}
+
+impl ChunkOrder {
+    fn layout(self, unknown: usize) -> Vec<ChunkKind> {
+        let mut layout = vec![ChunkKind::Format, ChunkKind::Fact, ChunkKind::Peak];
+        let others = [
+            ChunkKind::Bext,
+            ChunkKind::Cart,
+            ChunkKind::Ixml,
+            ChunkKind::Info,
+            ChunkKind::Id3,
+            ChunkKind::Cue,
+            ChunkKind::Playlist,
+            ChunkKind::AssociatedData,
+            ChunkKind::Sampler,
+            ChunkKind::Instrument,
+            ChunkKind::Acid,
+            ChunkKind::Chna,
+            ChunkKind::Axml,
+        ]
+        .into_iter()
+        .chain(iter::repeat_n(ChunkKind::Unknown, unknown));
+        match self {
+            ChunkOrder::DataLast => {
+                layout.extend(others);
+                layout.push(ChunkKind::Data);
+            }
+            ChunkOrder::DataFirst => {
+                layout.push(ChunkKind::Data);
+                layout.extend(others);
+            }
+        }
+        layout
+    }
+}
+
+/// Identifies which chunk of a [`Wave`] is written at a position in the file. Unknown chunks are
+/// written in the order of [`Wave::unknown`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChunkKind {
+    Format,
+    Fact,
+    Peak,
+    Bext,
+    Cart,
+    Ixml,
+    Info,
+    Id3,
+    Cue,
+    Playlist,
+    AssociatedData,
+    Sampler,
+    Instrument,
+    Acid,
+    Chna,
+    Axml,
+    Data,
+    Unknown,
+}
+
+impl ChunkKind {
+    /// The kind a chunk with `id` is read as, where `list_type` is the start of the body of LIST
+    /// chunks. `None` for chunks that are always unknown.
+    fn of(id: &[u8; 4], list_type: &[u8; 4]) -> Option<ChunkKind> {
+        Some(match (id, list_type) {
+            (b"fmt ", _) => ChunkKind::Format,
+            (b"fact", _) => ChunkKind::Fact,
+            (b"PEAK", _) => ChunkKind::Peak,
+            (b"bext", _) => ChunkKind::Bext,
+            (b"cart", _) => ChunkKind::Cart,
+            (b"iXML", _) => ChunkKind::Ixml,
+            (b"LIST", b"INFO") => ChunkKind::Info,
+            (b"id3 " | b"ID3 ", _) => ChunkKind::Id3,
+            (b"cue ", _) => ChunkKind::Cue,
+            (b"plst", _) => ChunkKind::Playlist,
+            (b"LIST", b"adtl") => ChunkKind::AssociatedData,
+            (b"smpl", _) => ChunkKind::Sampler,
+            (b"inst", _) => ChunkKind::Instrument,
+            (b"acid", _) => ChunkKind::Acid,
+            (b"chna", _) => ChunkKind::Chna,
+            (b"axml", _) => ChunkKind::Axml,
+            (b"data", _) => ChunkKind::Data,
+            _ => return None,
+        })
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Wave {
-    riff: RiffChunk,
+    pub container: Container,
+    /// The ds64 chunk of an RF64 or BW64 file.
+    pub ds64: Option<Ds64Chunk>,
     pub format: FormatChunk,
     pub data: DataChunk,
     pub fact: Option<FactChunk>,
     pub peak: Option<PeakChunk>,
+    /// The Broadcast Wave Format metadata.
+    pub bext: Option<BextChunk>,
+    pub cart: Option<CartChunk>,
+    pub ixml: Option<IxmlChunk>,
+    /// The LIST chunk of type INFO.
+    pub info: Option<InfoChunk>,
+    /// The ID3v2 tag of the id3 chunk.
+    pub id3: Option<Id3Chunk>,
+    pub cue: Option<CueChunk>,
+    pub playlist: Option<PlaylistChunk>,
+    /// The LIST chunk of type adtl, with labels and text for the points of [`Wave::cue`].
+    pub associated_data: Option<AssociatedDataList>,
+    /// The smpl chunk.
+    pub sampler: Option<SamplerChunk>,
+    /// The inst chunk.
+    pub instrument: Option<InstrumentChunk>,
+    pub acid: Option<AcidChunk>,
+    pub chna: Option<ChnaChunk>,
+    /// The body of the axml chunk, holding ADM metadata as XML.
+    pub axml: Option<Vec<u8>>,
+    /// Chunks that are not otherwise understood, in the order they appear in the file.
+    pub unknown: Vec<UnknownChunk>,
+    order: Vec<ChunkKind>,
 }
 impl Wave {
-    pub fn from_reader<T: io::Seek + io::Read>(mut reader: T) -> Result<Wave> {
-        let my_file: MyFile = MyFile::read(&mut reader)?;
-
-        let mut riff = None;
-        let mut format = None;
-        let mut data = None;
-        let mut fact = None;
-        let mut peak = None;
-
-        for chunk in my_file.chunks {
-            match chunk {
-                Chunk::Riff(chunk) => riff = Some(chunk),
-                Chunk::Data(chunk) => data = Some(chunk),
-                Chunk::Format(chunk) => format = Some(chunk),
-                Chunk::Fact(chunk) => fact = Some(chunk),
-                Chunk::Peak(chunk) => peak = Some(chunk),
-                Chunk::Empty => (),
-                Chunk::Eof => (),
+    /// Reads a file, working around the problems listed in [`ParseWarningKind`].
+    pub fn from_reader<T: io::Seek + io::Read>(reader: T) -> Result<Wave> {
+        Ok(Wave::from_reader_with_options(reader, ParseOptions::default())?.0)
+    }
+
+    /// Reads a file, along with the problems that were worked around in the order they were
+    /// found. Strict parsing returns no warnings.
+    pub fn from_reader_with_options<T: io::Seek + io::Read>(
+        mut reader: T,
+        options: ParseOptions,
+    ) -> Result<(Wave, Vec<ParseWarning>)> {
+        let (wave, _, warnings) = Wave::read_chunks(&mut reader, true, options)?;
+        Ok((wave, warnings))
+    }
+
+    /// Reads every chunk, along with the offset of the body of the data chunk. Without
+    /// `load_data` the data chunk is skipped, leaving its body empty.
+    pub(crate) fn read_chunks<T: io::Seek + io::Read>(
+        reader: &mut T,
+        load_data: bool,
+        options: ParseOptions,
+    ) -> Result<(Wave, u64, Vec<ParseWarning>)> {
+        let mut warnings = Vec::new();
+        let mut warn = |offset: u64, kind: ParseWarningKind| -> Result<()> {
+            let warning = ParseWarning { offset, kind };
+            if options.strict {
+                return Err(WaverlyError::Malformed(warning));
+            }
+            warnings.push(warning);
+            Ok(())
+        };
+
+        let riff: ChunkHeader = reader.read_le()?;
+        let container = match &riff.id {
+            b"RIFF" => Container::Riff,
+            b"RF64" => Container::Rf64,
+            b"BW64" => Container::Bw64,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "RIFF chunk was not found in file.",
+                )
+                .into())
             }
+        };
+
+        let form_type: [u8; 4] = reader.read_le()?;
+        if &form_type != b"WAVE" {
+            return Err(
+                io::Error::new(io::ErrorKind::InvalidInput, "RIFF form type is not WAVE.").into(),
+            );
         }
 
-        if riff == None {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "RIFF chunk was not found in file.",
-            )
-            .into());
+        let mut ds64: Option<Ds64Chunk> = None;
+        let mut format: Option<FormatChunk> = None;
+        let mut data: Option<DataChunk> = None;
+        let mut fact: Option<FactChunk> = None;
+        let mut peak: Option<PeakChunk> = None;
+        let mut peak_start = 0;
+        let mut bext: Option<BextChunk> = None;
+        let mut cart: Option<CartChunk> = None;
+        let mut ixml: Option<IxmlChunk> = None;
+        let mut info: Option<InfoChunk> = None;
+        let mut id3: Option<Id3Chunk> = None;
+        let mut cue: Option<CueChunk> = None;
+        let mut playlist: Option<PlaylistChunk> = None;
+        let mut associated_data: Option<AssociatedDataList> = None;
+        let mut sampler: Option<SamplerChunk> = None;
+        let mut instrument: Option<InstrumentChunk> = None;
+        let mut acid: Option<AcidChunk> = None;
+        let mut chna: Option<ChnaChunk> = None;
+        let mut axml: Option<Vec<u8>> = None;
+        let mut unknown = Vec::new();
+        let mut order = Vec::new();
+        let mut data_offset = 0;
+
+        let mut start = stream_position(reader)?;
+        let end = reader.seek(io::SeekFrom::End(0))?;
+        // The RIFF size of RF64 and BW64 files is in the ds64 chunk.
+        if !container.has_ds64() && riff.size as u64 != end - 8 {
+            warn(
+                4,
+                ParseWarningKind::RiffSize {
+                    stored: riff.size as u64,
+                    actual: end - 8,
+                },
+            )?;
+        }
+        while start < end {
+            if !chunk_id_at(reader, start, end)? {
+                warn(start, ParseWarningKind::TrailingBytes { len: end - start })?;
+                break;
+            }
+            reader.seek(io::SeekFrom::Start(start))?;
+            let header: ChunkHeader = reader.read_le()?;
+            let size = match (&ds64, header.size) {
+                (Some(ds64), u32::MAX) => ds64.chunk_size(&header.id).unwrap_or(u32::MAX as u64),
+                _ => header.size as u64,
+            };
+            let available = end - (start + 8);
+            // Only the first chunk of each kind is parsed, later ones are kept as is.
+            let mut list_type = [0u8; 4];
+            if &header.id == b"LIST" && size.min(available) >= 4 {
+                list_type = reader.read_le()?;
+            }
+            let duplicate = matches!(
+                ChunkKind::of(&header.id, &list_type),
+                Some(kind) if order.contains(&kind)
+            );
+            if size > available {
+                warn(
+                    start,
+                    ParseWarningKind::TruncatedChunk {
+                        id: header.id,
+                        size,
+                        available,
+                    },
+                )?;
+                if &header.id != b"data" || duplicate {
+                    break;
+                }
+            }
+            if duplicate {
+                warn(start, ParseWarningKind::DuplicateChunk { id: header.id })?;
+            }
+
+            let kind = if duplicate {
+                let data = read_body(reader, start, size)?;
+                unknown.push(UnknownChunk {
+                    id: header.id,
+                    data,
+                });
+                ChunkKind::Unknown
+            } else {
+                // Typed chunks read their own id and size.
+                reader.seek(io::SeekFrom::Start(start))?;
+                match &header.id {
+                    b"ds64" if container.has_ds64() => {
+                        let chunk: Ds64Chunk = reader.read_le()?;
+                        if chunk.riff_size != end - 8 {
+                            warn(
+                                start + 8,
+                                ParseWarningKind::RiffSize {
+                                    stored: chunk.riff_size,
+                                    actual: end - 8,
+                                },
+                            )?;
+                        }
+                        ds64 = Some(chunk);
+                        start += 8 + size + size % 2;
+                        continue;
+                    }
+                    b"fmt " => {
+                        // Read through a copy, so that a bogus extension size cannot run past the
+                        // end of the chunk.
+                        let mut body = vec![0; 8 + size as usize];
+                        reader.read_exact(&mut body)?;
+                        if size >= 18 {
+                            let stored = u16::from_le_bytes([body[24], body[25]]);
+                            let actual = (size - 18).min(u16::MAX as u64) as u16;
+                            if stored != actual {
+                                warn(
+                                    start + 24,
+                                    ParseWarningKind::ExtensionSize { stored, actual },
+                                )?;
+                                body[24..26].copy_from_slice(&actual.to_le_bytes());
+                            }
+                        }
+                        format = Some(io::Cursor::new(body).read_le()?);
+                        ChunkKind::Format
+                    }
+                    b"data" => {
+                        data_offset = start + 8;
+                        let mut body = Vec::new();
+                        if load_data {
+                            reader.seek(io::SeekFrom::Start(data_offset))?;
+                            body = vec![0; size.min(available) as usize];
+                            reader.read_exact(&mut body)?;
+                        }
+                        data = Some(DataChunk { size, data: body });
+                        ChunkKind::Data
+                    }
+                    id @ (b"fact" | b"PEAK" | b"cue " | b"plst" | b"smpl" | b"inst" | b"acid"
+                    | b"chna") => {
+                        // Parsed from a copy as well, so that counts that do not fit the chunk
+                        // cannot run past its end.
+                        let mut chunk = vec![0; 8 + size as usize];
+                        reader.read_exact(&mut chunk)?;
+                        let kind = match id {
+                            b"fact" => {
+                                fact = parse_whole(&chunk, |cursor| cursor.read_le());
+                                fact.is_some().then_some(ChunkKind::Fact)
+                            }
+                            b"PEAK" => {
+                                peak = parse_whole(&chunk, |cursor| cursor.read_le());
+                                peak_start = start;
+                                peak.is_some().then_some(ChunkKind::Peak)
+                            }
+                            b"cue " => {
+                                cue = CueChunk::parse(&chunk);
+                                cue.is_some().then_some(ChunkKind::Cue)
+                            }
+                            b"plst" => {
+                                playlist = PlaylistChunk::parse(&chunk);
+                                playlist.is_some().then_some(ChunkKind::Playlist)
+                            }
+                            b"smpl" => {
+                                sampler = SamplerChunk::parse(&chunk);
+                                sampler.is_some().then_some(ChunkKind::Sampler)
+                            }
+                            b"inst" => {
+                                instrument = parse_whole(&chunk, |cursor| cursor.read_le());
+                                instrument.is_some().then_some(ChunkKind::Instrument)
+                            }
+                            b"acid" => {
+                                acid = AcidChunk::parse(&chunk);
+                                acid.is_some().then_some(ChunkKind::Acid)
+                            }
+                            _ => {
+                                chna = ChnaChunk::parse(&chunk);
+                                chna.is_some().then_some(ChunkKind::Chna)
+                            }
+                        };
+                        match kind {
+                            Some(kind) => kind,
+                            None => {
+                                warn(start, ParseWarningKind::InvalidChunk { id: *id })?;
+                                unknown.push(UnknownChunk {
+                                    id: *id,
+                                    data: chunk.split_off(8),
+                                });
+                                ChunkKind::Unknown
+                            }
+                        }
+                    }
+                    id => {
+                        let data = read_body(reader, start, size)?;
+                        match (id, data.get(..4)) {
+                            (b"LIST", Some(b"INFO")) => {
+                                info = Some(InfoChunk::parse(&data[4..]));
+                                ChunkKind::Info
+                            }
+                            (b"LIST", Some(b"adtl")) => {
+                                associated_data = Some(AssociatedDataList::parse(&data[4..]));
+                                ChunkKind::AssociatedData
+                            }
+                            (b"bext", _) => match BextChunk::parse(&data) {
+                                Some(chunk) => {
+                                    bext = Some(chunk);
+                                    ChunkKind::Bext
+                                }
+                                None => {
+                                    warn(start, ParseWarningKind::InvalidChunk { id: *id })?;
+                                    unknown.push(UnknownChunk { id: *id, data });
+                                    ChunkKind::Unknown
+                                }
+                            },
+                            (b"cart", _) => match CartChunk::parse(&data) {
+                                Some(chunk) => {
+                                    cart = Some(chunk);
+                                    ChunkKind::Cart
+                                }
+                                None => {
+                                    warn(start, ParseWarningKind::InvalidChunk { id: *id })?;
+                                    unknown.push(UnknownChunk { id: *id, data });
+                                    ChunkKind::Unknown
+                                }
+                            },
+                            (b"id3 " | b"ID3 ", _) => match Id3Chunk::parse(*id, &data) {
+                                Some(tag) => {
+                                    id3 = Some(tag);
+                                    ChunkKind::Id3
+                                }
+                                None => {
+                                    warn(start, ParseWarningKind::InvalidChunk { id: *id })?;
+                                    unknown.push(UnknownChunk { id: *id, data });
+                                    ChunkKind::Unknown
+                                }
+                            },
+                            (b"iXML", _) => match IxmlChunk::parse(&data) {
+                                Some(chunk) => {
+                                    ixml = Some(chunk);
+                                    ChunkKind::Ixml
+                                }
+                                None => {
+                                    warn(start, ParseWarningKind::InvalidChunk { id: *id })?;
+                                    unknown.push(UnknownChunk { id: *id, data });
+                                    ChunkKind::Unknown
+                                }
+                            },
+                            (b"axml", _) => {
+                                axml = Some(data);
+                                ChunkKind::Axml
+                            }
+                            _ => {
+                                unknown.push(UnknownChunk { id: *id, data });
+                                ChunkKind::Unknown
+                            }
+                        }
+                    }
+                }
+            };
+            order.push(kind);
+
+            let next = start + 8 + size;
+            start = next + size % 2;
+            let pad_missing = size % 2 == 1
+                && next < end
+                && !chunk_id_at(reader, start, end)?
+                && chunk_id_at(reader, next, end)?;
+            if size % 2 == 1 && (next == end || pad_missing) {
+                warn(next, ParseWarningKind::MissingPadByte { id: header.id })?;
+                start = next;
+            }
         }
 
-        if format == None {
+        if format.is_none() {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "FORMAT chunk was not found in file.",
@@ -250,7 +1155,7 @@ impl Wave {
             .into());
         }
 
-        if data == None {
+        if data.is_none() {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "DATA chunk was not found in file.",
@@ -259,7 +1164,9 @@ impl Wave {
         }
 
         let format = format.unwrap();
-        if format.audio_format != WaveFormat::Pcm && fact == None {
+        // Formats that cannot be decoded are still read, for the sake of their metadata.
+        let decodable = sample::bytes_per_sample(&format).is_ok();
+        if decodable && format.effective_format() != WaveFormat::Pcm && fact.is_none() {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
                 "FACT format is required for non-PCM WAV formats",
@@ -267,22 +1174,459 @@ impl Wave {
             .into());
         }
 
-        Ok(Wave {
-            riff: riff.unwrap(),
+        if let WaveFormat::Pcm | WaveFormat::IeeeFloat = format.effective_format() {
+            format.validate_bit_depth()?;
+        }
+
+        // A PEAK chunk that does not hold one peak for each channel is kept as is, in its place.
+        let num_channels = format.num_channels as usize;
+        if let Some(chunk) = peak.take_if(|chunk| chunk.peaks.len() != num_channels) {
+            warn(peak_start, ParseWarningKind::InvalidChunk { id: *b"PEAK" })?;
+            let index = order
+                .iter()
+                .position(|&kind| kind == ChunkKind::Peak)
+                .unwrap();
+            let before = order[..index]
+                .iter()
+                .filter(|&&kind| kind == ChunkKind::Unknown)
+                .count();
+            order[index] = ChunkKind::Unknown;
+            unknown.insert(
+                before,
+                UnknownChunk {
+                    id: *b"PEAK",
+                    data: read_body(reader, peak_start, chunk.size as u64)?,
+                },
+            );
+        }
+
+        let wave = Wave {
+            container,
+            ds64,
             data: data.unwrap(),
             format,
             fact,
             peak,
-        })
+            bext,
+            cart,
+            ixml,
+            info,
+            id3,
+            cue,
+            playlist,
+            associated_data,
+            sampler,
+            instrument,
+            acid,
+            chna,
+            axml,
+            unknown,
+            order,
+        };
+        Ok((wave, data_offset, warnings))
+    }
+
+    /// Decodes the data chunk into samples of type `S`.
+    ///
+    /// Supports PCM in 8, 16, 24 and 32-bit containers with any number of valid bits, 32 and
+    /// 64-bit IEEE float, A-law, µ-law, IMA ADPCM and MS ADPCM. Samples of multi-channel files are
+    /// interleaved. Block based formats are decoded up front, and the padding of their last block
+    /// is dropped according to the FACT chunk.
+    pub fn samples<S: Sample>(&self) -> Result<Samples<'_, S>> {
+        let mut samples = Samples::new(&self.format, &self.data.data)?;
+        if let Some(fact) = &self.fact {
+            samples.truncate(fact.data as usize * self.format.num_channels as usize);
+        }
+        Ok(samples)
+    }
+
+    /// Replaces the PEAK chunk with one computed from the data chunk, dated `timestamp` seconds
+    /// after the Unix epoch. Call before [`Wave::write`] when the samples have changed.
+    pub fn update_peak(&mut self, timestamp: u32) -> Result<()> {
+        self.peak = Some(PeakChunk::from_samples(
+            &self.format,
+            &self.data.data,
+            timestamp,
+        )?);
+        Ok(())
+    }
+
+    /// The text of an INFO tag, without its NUL terminator. See [`InfoEntry::text`].
+    pub fn info_tag(&self, tag: InfoTag) -> Option<Cow<'_, str>> {
+        self.info.as_ref()?.get(tag)
+    }
+
+    /// Sets the text of an INFO tag, adding a LIST chunk of type INFO if there is none.
+    pub fn set_info_tag(&mut self, tag: InfoTag, value: &str) {
+        self.info
+            .get_or_insert_with(InfoChunk::default)
+            .set(tag, value);
+    }
+
+    /// Adds a cue point at `position` sample frames, labeled `label`, and returns its id.
+    pub fn add_marker(&mut self, position: u32, label: &str) -> u32 {
+        let cue = self.cue.get_or_insert_with(CueChunk::default);
+        let id = cue.next_id();
+        cue.cue_points.push(CuePoint::new(id, position));
+        self.associated_data
+            .get_or_insert_with(AssociatedDataList::default)
+            .set_label(id, label);
+        id
+    }
+
+    /// Adds a region of `length` sample frames starting at `position`, labeled `label`, and
+    /// returns the id of its cue point.
+    pub fn add_region(&mut self, position: u32, length: u32, label: &str) -> u32 {
+        let id = self.add_marker(position, label);
+        if let Some(list) = &mut self.associated_data {
+            list.entries.push(AssociatedData::LabeledText(LabeledText {
+                cue_id: id,
+                sample_length: length,
+                purpose: *b"rgn ",
+                country: 0,
+                language: 0,
+                dialect: 0,
+                code_page: 0,
+                data: Vec::new(),
+            }));
+        }
+        id
+    }
+
+    /// Sets the label of a marker or region.
+    pub fn rename_cue(&mut self, id: u32, label: &str) {
+        self.associated_data
+            .get_or_insert_with(AssociatedDataList::default)
+            .set_label(id, label);
+    }
+
+    /// Removes a marker or region, along with its labels, notes and text. Returns whether the
+    /// cue point existed.
+    pub fn remove_cue(&mut self, id: u32) -> bool {
+        if let Some(list) = &mut self.associated_data {
+            list.remove(id);
+        }
+        match &mut self.cue {
+            Some(cue) => {
+                let count = cue.cue_points.len();
+                cue.cue_points.retain(|cue_point| cue_point.id != id);
+                cue.cue_points.len() != count
+            }
+            None => false,
+        }
+    }
+
+    /// Appends a segment to the playlist, adding a playlist chunk if there is none. Fails if the
+    /// cue point does not exist.
+    pub fn add_playlist_segment(&mut self, cue_id: u32, length: u32, loops: u32) -> Result<()> {
+        if self.cue.as_ref().and_then(|cue| cue.get(cue_id)).is_none() {
+            return Err(WaverlyError::UnknownCuePoint(cue_id));
+        }
+        self.playlist
+            .get_or_insert_with(PlaylistChunk::default)
+            .segments
+            .push(Segment {
+                cue_id,
+                length,
+                loops,
+            });
+        Ok(())
+    }
+
+    /// Checks that every playlist segment refers to an existing cue point.
+    pub fn validate_playlist(&self) -> Result<()> {
+        if let Some(playlist) = &self.playlist {
+            if let Some(id) = playlist.missing_cue_ids(self.cue.as_ref()).next() {
+                return Err(WaverlyError::UnknownCuePoint(id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Discards the order chunks were read in, placing them as described by `order` instead.
+    pub fn set_chunk_order(&mut self, order: ChunkOrder) {
+        self.order = order.layout(self.unknown.len());
+    }
+
+    /// The order chunks are written in. Chunks added since the order was recorded are placed
+    /// before the data chunk.
+    fn layout(&self) -> Vec<ChunkKind> {
+        let mut added = Vec::new();
+        let optional = [
+            (ChunkKind::Fact, self.fact.is_some()),
+            (ChunkKind::Peak, self.peak.is_some()),
+            (ChunkKind::Bext, self.bext.is_some()),
+            (ChunkKind::Cart, self.cart.is_some()),
+            (ChunkKind::Ixml, self.ixml.is_some()),
+            (ChunkKind::Info, self.info.is_some()),
+            (ChunkKind::Id3, self.id3.is_some()),
+            (ChunkKind::Cue, self.cue.is_some()),
+            (ChunkKind::Playlist, self.playlist.is_some()),
+            (ChunkKind::AssociatedData, self.associated_data.is_some()),
+            (ChunkKind::Sampler, self.sampler.is_some()),
+            (ChunkKind::Instrument, self.instrument.is_some()),
+            (ChunkKind::Acid, self.acid.is_some()),
+            (ChunkKind::Chna, self.chna.is_some()),
+            (ChunkKind::Axml, self.axml.is_some()),
+        ];
+        for (kind, present) in optional {
+            if present && !self.order.contains(&kind) {
+                added.push(kind);
+            }
+        }
+        let recorded = self
+            .order
+            .iter()
+            .filter(|&&kind| kind == ChunkKind::Unknown)
+            .count();
+        let unknown = self.unknown.len().saturating_sub(recorded);
+        added.extend(iter::repeat_n(ChunkKind::Unknown, unknown));
+
+        let mut layout = self.order.clone();
+        let data = layout
+            .iter()
+            .position(|&kind| kind == ChunkKind::Data)
+            .unwrap_or(layout.len());
+        layout.splice(data..data, added);
+        layout
+    }
+
+    /// The size of the RIFF chunk when `layout` is written as a RIFF file, without a ds64 chunk.
+    fn riff_size(&self, layout: &[ChunkKind]) -> u64 {
+        let mut unknown = self.unknown.iter();
+        let mut size = 4;
+        for kind in layout {
+            let body = match kind {
+                ChunkKind::Format => Some(self.format.size as u64),
+                ChunkKind::Fact => self.fact.as_ref().map(|fact| fact.size as u64),
+                ChunkKind::Peak => self.peak.as_ref().map(|peak| peak.size as u64),
+                ChunkKind::Bext => self.bext.as_ref().map(|bext| bext.to_bytes().len() as u64),
+                ChunkKind::Cart => self.cart.as_ref().map(|cart| cart.to_bytes().len() as u64),
+                ChunkKind::Ixml => self.ixml.as_ref().map(|ixml| ixml.to_bytes().len() as u64),
+                ChunkKind::Info => self.info.as_ref().map(|info| info.to_bytes().len() as u64),
+                ChunkKind::Id3 => self.id3.as_ref().map(|id3| id3.to_bytes().len() as u64),
+                ChunkKind::Cue => self.cue.as_ref().map(|cue| cue.size as u64),
+                ChunkKind::Playlist => self.playlist.as_ref().map(|playlist| playlist.size as u64),
+                ChunkKind::AssociatedData => self
+                    .associated_data
+                    .as_ref()
+                    .map(|list| list.to_bytes().len() as u64),
+                ChunkKind::Sampler => self.sampler.as_ref().map(|sampler| sampler.size as u64),
+                ChunkKind::Instrument => self
+                    .instrument
+                    .as_ref()
+                    .map(|instrument| instrument.size as u64),
+                ChunkKind::Acid => self.acid.as_ref().map(|acid| acid.size as u64),
+                ChunkKind::Chna => self.chna.as_ref().map(|chna| chna.size as u64),
+                ChunkKind::Axml => self.axml.as_ref().map(|axml| axml.len() as u64),
+                ChunkKind::Data => Some(self.data.size),
+                ChunkKind::Unknown => unknown.next().map(|chunk| chunk.data.len() as u64),
+            };
+            if let Some(body) = body {
+                size += 8 + body + body % 2;
+            }
+        }
+        size
     }
 
-    pub fn write<T: io::Seek + io::Write>(self, mut writer: T) -> Result<()> {
-        self.write_to(&mut writer)?;
+    /// Writes the WAV file, with chunks in the order they were read in, first updating the RIFF
+    /// size, DATA size and FACT sample count to match the current contents.
+    ///
+    /// A RIFF file that would exceed 4 GiB is written as RF64. The ds64 chunk of RF64 and BW64
+    /// files is recomputed, listing the unknown chunks of 4 GiB and over. Fails if the bext or cart
+    /// chunk does not pass [`BextChunk::validate`] or [`CartChunk::validate`].
+    pub fn write<T: io::Seek + io::Write>(mut self, mut writer: T) -> Result<()> {
+        self.data.size = self.data.data.len() as u64;
+        let frame_size = sample::bytes_per_sample(&self.format)
+            .map(|bytes| bytes * self.format.num_channels as usize);
+        let frames = frame_size
+            .ok()
+            .and_then(|frame_size| self.data.data.len().checked_div(frame_size));
+        if let Some(fact) = &mut self.fact {
+            if let Some(frames) = frames {
+                fact.data = u32::try_from(frames).unwrap_or(u32::MAX);
+            }
+            fact.size = 4 + fact.trailing.len() as u32;
+        }
+        self.format.update_size();
+        if let Some(peak) = &mut self.peak {
+            peak.size = 8 + 8 * peak.peaks.len() as u32;
+        }
+        if let Some(cue) = &mut self.cue {
+            cue.update_size();
+        }
+        if let Some(playlist) = &mut self.playlist {
+            playlist.update_size();
+        }
+        if let Some(sampler) = &mut self.sampler {
+            sampler.update_size();
+        }
+        if let Some(instrument) = &mut self.instrument {
+            instrument.size = 7;
+        }
+        if let Some(acid) = &mut self.acid {
+            acid.size = 24;
+        }
+        if let Some(chna) = &mut self.chna {
+            chna.update_size();
+        }
+        if let Some(bext) = &self.bext {
+            bext.validate()?;
+        }
+        if let Some(cart) = &self.cart {
+            cart.validate()?;
+        }
+
+        let layout = self.layout();
+        let container = match self.container {
+            Container::Riff if self.riff_size(&layout) > u32::MAX as u64 => Container::Rf64,
+            container => container,
+        };
+
+        let start = stream_position(&mut writer)?;
+        writer.write_le(&ChunkHeader {
+            id: container.id(),
+            size: 0,
+        })?;
+        writer.write_all(b"WAVE")?;
+        if container.has_ds64() {
+            let sample_count = match (frames, &self.fact) {
+                (Some(frames), _) => frames as u64,
+                (None, Some(fact)) => fact.data as u64,
+                (None, None) => 0,
+            };
+            let table = self
+                .unknown
+                .iter()
+                .filter(|chunk| chunk.data.len() as u64 > u32::MAX as u64)
+                .map(|chunk| ChunkSize {
+                    id: chunk.id,
+                    size: chunk.data.len() as u64,
+                })
+                .collect();
+            writer.write_le(&Ds64Chunk::new(self.data.size, sample_count, table))?;
+        }
+        let mut unknown = self.unknown.iter();
+        for kind in layout {
+            match kind {
+                ChunkKind::Format => writer.write_le(&self.format)?,
+                ChunkKind::Fact => {
+                    if let Some(fact) = &self.fact {
+                        writer.write_le(fact)?;
+                        write_pad_byte(&mut writer, fact.size as usize)?;
+                    }
+                }
+                ChunkKind::Peak => {
+                    if let Some(peak) = &self.peak {
+                        writer.write_le(peak)?;
+                    }
+                }
+                ChunkKind::Bext => {
+                    if let Some(bext) = &self.bext {
+                        write_raw_chunk(&mut writer, *b"bext", &bext.to_bytes())?;
+                    }
+                }
+                ChunkKind::Cart => {
+                    if let Some(cart) = &self.cart {
+                        write_raw_chunk(&mut writer, *b"cart", &cart.to_bytes())?;
+                    }
+                }
+                ChunkKind::Ixml => {
+                    if let Some(ixml) = &self.ixml {
+                        write_raw_chunk(&mut writer, *b"iXML", &ixml.to_bytes())?;
+                    }
+                }
+                ChunkKind::Info => {
+                    if let Some(info) = &self.info {
+                        write_raw_chunk(&mut writer, *b"LIST", &info.to_bytes())?;
+                    }
+                }
+                ChunkKind::Id3 => {
+                    if let Some(id3) = &self.id3 {
+                        write_raw_chunk(&mut writer, id3.id, &id3.to_bytes())?;
+                    }
+                }
+                ChunkKind::Cue => {
+                    if let Some(cue) = &self.cue {
+                        writer.write_le(cue)?;
+                    }
+                }
+                ChunkKind::Playlist => {
+                    if let Some(playlist) = &self.playlist {
+                        writer.write_le(playlist)?;
+                    }
+                }
+                ChunkKind::AssociatedData => {
+                    if let Some(list) = &self.associated_data {
+                        write_raw_chunk(&mut writer, *b"LIST", &list.to_bytes())?;
+                    }
+                }
+                ChunkKind::Sampler => {
+                    if let Some(sampler) = &self.sampler {
+                        writer.write_le(sampler)?;
+                        write_pad_byte(&mut writer, sampler.size as usize)?;
+                    }
+                }
+                ChunkKind::Instrument => {
+                    if let Some(instrument) = &self.instrument {
+                        writer.write_le(instrument)?;
+                        write_pad_byte(&mut writer, instrument.size as usize)?;
+                    }
+                }
+                ChunkKind::Acid => {
+                    if let Some(acid) = &self.acid {
+                        writer.write_le(acid)?;
+                    }
+                }
+                ChunkKind::Chna => {
+                    if let Some(chna) = &self.chna {
+                        writer.write_le(chna)?;
+                    }
+                }
+                ChunkKind::Axml => {
+                    if let Some(axml) = &self.axml {
+                        write_raw_chunk(&mut writer, *b"axml", axml)?;
+                    }
+                }
+                ChunkKind::Data => {
+                    if container.has_ds64() {
+                        writer.write_le(&ChunkHeader {
+                            id: *b"data",
+                            size: u32::MAX,
+                        })?;
+                        writer.write_all(&self.data.data)?;
+                    } else {
+                        writer.write_le(&self.data)?;
+                    }
+                    write_pad_byte(&mut writer, self.data.data.len())?;
+                }
+                ChunkKind::Unknown => {
+                    // Unknown chunks that were removed since reading leave their slot empty.
+                    if let Some(chunk) = unknown.next() {
+                        write_raw_chunk(&mut writer, chunk.id, &chunk.data)?;
+                    }
+                }
+            }
+        }
+        let end = stream_position(&mut writer)?;
+
+        // The RIFF size covers everything after the size field itself.
+        let riff_size = end - start - 8;
+        writer.seek(io::SeekFrom::Start(start + 4))?;
+        if container.has_ds64() {
+            writer.write_all(&u32::MAX.to_le_bytes())?;
+            // The 64-bit RIFF size follows the id and size of the ds64 chunk.
+            writer.seek(io::SeekFrom::Start(start + 20))?;
+            writer.write_all(&riff_size.to_le_bytes())?;
+        } else {
+            writer.write_all(&(riff_size as u32).to_le_bytes())?;
+        }
+        writer.seek(io::SeekFrom::Start(end))?;
         Ok(())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     extern crate std;
     use super::*;
@@ -290,7 +1634,6 @@ mod tests {
     use std::fs::File;
     use std::io::Cursor;
 
-    #[cfg(feature = "std")]
     #[test]
     fn it_reads_format() -> Result<()> {
         let file = File::open("./meta/16bit-2ch-float-peak.wav")?;
@@ -299,11 +1642,11 @@ mod tests {
         let f = &wave.format;
         assert_eq!(f.sample_rate, 44100);
 
-        assert_eq!(f.bits_per_sample, BitDepth::SixtyFour);
+        assert_eq!(f.bits_per_sample, 64);
         assert_eq!(f.num_channels, 2);
         assert_eq!(f.audio_format, WaveFormat::IeeeFloat);
 
-        let block_align = f.num_channels * (f.bits_per_sample as u16) / 8;
+        let block_align = f.num_channels * f.bits_per_sample / 8;
         let byte_rate = f.sample_rate * block_align as u32;
         assert_eq!(f.byte_rate, byte_rate);
         assert_eq!(f.byte_rate, 705600);
@@ -314,7 +1657,6 @@ mod tests {
         Ok(())
     }
 
-    #[cfg(feature = "std")]
     #[test]
     fn it_writes_data_correctly() -> Result<()> {
         let filename = "./meta/16bit-2ch-float-peak.wav";
@@ -325,9 +1667,8 @@ mod tests {
         let mut virt_file = Cursor::new(Vec::new());
         wave.write(&mut virt_file)?;
         let buf = virt_file.into_inner();
-        // Test WAV file is improper and includes
-        // two bytes for Extensible data incorrectly.
         assert_eq!(buf.len(), metadata.len() as usize);
+        assert_eq!(buf, fs::read(filename)?);
         assert_ne!(buf.len(), 0);
         let buf_iter = buf.into_iter();
         let riff_magic: Vec<u8> = buf_iter.take(4).collect();
@@ -335,4 +1676,973 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn it_decodes_samples() -> Result<()> {
+        let file = File::open("./meta/16bit-2ch-float-peak.wav")?;
+        let wave: Wave = Wave::from_reader(file)?;
+
+        let samples: Vec<f64> = wave.samples()?.collect();
+        assert_eq!(samples.len(), wave.data.data.len() / 8);
+        assert_eq!(samples[0], f64::from_bits(0x3F99_1000_0000_0000));
+
+        let peak = &wave.peak.unwrap().peaks[0];
+        let frame = peak.position as usize * 2;
+        assert_eq!(samples[frame].abs() as f32, peak.value);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_built_waves() -> Result<()> {
+        let samples: Vec<i16> = (0..1000).map(|i| (i * 37 % 2000 - 1000) as i16).collect();
+        let wave = WaveBuilder::new(22050, 2, WaveFormat::Mulaw, 8).build(&samples)?;
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        assert_eq!(
+            u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize,
+            buf.len() - 8
+        );
+
+        let wave = Wave::from_reader(Cursor::new(buf))?;
+        assert_eq!(wave.format.block_align, 2);
+        assert_eq!(wave.fact.map(|fact| fact.data), Some(500));
+        assert_eq!(wave.data.data.len(), 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn it_keeps_the_rest_of_the_fact_chunk() -> Result<()> {
+        let wave = WaveBuilder::new(8000, 1, WaveFormat::Mulaw, 8).build(&[0i16; 4])?;
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+
+        // Grow the fact chunk to 8 bytes.
+        let fact = buf.windows(4).position(|id| id == b"fact").unwrap();
+        let mut buf = [&buf[..fact + 12], b"\x01\x02\x03\x04", &buf[fact + 12..]].concat();
+        buf[fact + 4] = 8;
+        let riff_size = buf.len() as u32 - 8;
+        buf[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        let wave = Wave::from_reader(Cursor::new(&buf))?;
+        let fact_chunk = wave.fact.as_ref().unwrap();
+        assert_eq!(fact_chunk.data, 4);
+        assert_eq!(fact_chunk.trailing, [1, 2, 3, 4]);
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+        Ok(())
+    }
+
+    #[test]
+    fn it_preserves_unknown_chunks() -> Result<()> {
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Mulaw, 8).build(&[0i16; 8])?;
+        wave.unknown.push(UnknownChunk {
+            id: *b"JUNK",
+            data: vec![1, 2, 3],
+        });
+        wave.unknown.push(UnknownChunk {
+            id: *b"minf",
+            data: vec![4; 8],
+        });
+        wave.set_chunk_order(ChunkOrder::DataFirst);
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        // Both chunks and the pad byte after the odd sized JUNK chunk.
+        assert_eq!(buf.len(), 12 + 24 + 16 + 12 + 12 + 16);
+
+        let wave = Wave::from_reader(Cursor::new(buf))?;
+        assert_eq!(wave.unknown[0].id, *b"JUNK");
+        assert_eq!(wave.unknown[0].data, [1, 2, 3]);
+        assert_eq!(wave.unknown[1].id, *b"minf");
+        assert_eq!(wave.unknown[1].data, [4; 8]);
+        assert_eq!(wave.data.data, [0xFF; 8]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_unknown_chunks_before_format() -> Result<()> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        buf.extend_from_slice(b"LIST\x05\0\0\0exif\0\0");
+        buf.extend_from_slice(b"fmt \x10\0\0\0\x03\0\x01\0\x40\x1f\0\0\0\x7d\0\0\x04\0\x20\0");
+        buf.extend_from_slice(b"fact\x04\0\0\0\x01\0\0\0");
+        buf.extend_from_slice(b"data\x04\0\0\0\0\0\0\xbf");
+
+        let wave = Wave::from_reader(Cursor::new(buf))?;
+        assert_eq!(wave.unknown[0].id, *b"LIST");
+        assert_eq!(wave.unknown[0].data, *b"exif\0");
+        assert_eq!(wave.format.sample_rate, 8000);
+        assert_eq!(wave.samples::<f32>()?.collect::<Vec<_>>(), [-0.5]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_places_added_chunks_before_data() -> Result<()> {
+        let file = File::open("./meta/16bit-2ch-float-peak.wav")?;
+        let mut wave: Wave = Wave::from_reader(file)?;
+        wave.unknown.push(UnknownChunk {
+            id: *b"JUNK",
+            data: vec![0; 4],
+        });
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        let ids: Vec<&[u8]> = [12, 36, 48, 80, 92]
+            .iter()
+            .map(|&i| &buf[i..i + 4])
+            .collect();
+        assert_eq!(ids, [b"fmt ", b"fact", b"PEAK", b"JUNK", b"data"]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_info_tags() -> Result<()> {
+        let file = File::open("./meta/16bit-2ch-float-peak.wav")?;
+        let mut wave: Wave = Wave::from_reader(file)?;
+        assert_eq!(wave.info_tag(InfoTag::Title).as_deref(), None);
+        wave.set_info_tag(InfoTag::Title, "Peak test");
+        wave.set_info_tag(InfoTag::Software, "waverly");
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+        assert_eq!(wave.info_tag(InfoTag::Title).as_deref(), Some("Peak test"));
+        assert_eq!(wave.info_tag(InfoTag::Software).as_deref(), Some("waverly"));
+        assert!(wave.unknown.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_markers_and_regions() -> Result<()> {
+        let file = File::open("./meta/16bit-2ch-float-peak.wav")?;
+        let mut wave: Wave = Wave::from_reader(file)?;
+        let marker = wave.add_marker(100, "Marker");
+        let region = wave.add_region(2000, 500, "Region");
+        let removed = wave.add_marker(3000, "Removed");
+        wave.rename_cue(marker, "Renamed");
+        assert!(wave.remove_cue(removed));
+        assert!(!wave.remove_cue(removed));
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+
+        let cue = wave.cue.unwrap();
+        assert_eq!(cue.num_cue_points, 2);
+        assert_eq!(
+            cue.get(marker).map(|cue_point| cue_point.sample_offset),
+            Some(100)
+        );
+        assert_eq!(
+            cue.get(region).map(|cue_point| cue_point.position),
+            Some(2000)
+        );
+        let list = wave.associated_data.unwrap();
+        assert_eq!(list.label(marker).as_deref(), Some("Renamed"));
+        assert_eq!(list.label(region).as_deref(), Some("Region"));
+        assert_eq!(
+            list.labeled_text(region).map(|text| text.sample_length),
+            Some(500)
+        );
+        assert_eq!(list.label(removed), None);
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_playlists() -> Result<()> {
+        let file = File::open("./meta/16bit-2ch-float-peak.wav")?;
+        let mut wave: Wave = Wave::from_reader(file)?;
+        let intro = wave.add_marker(0, "Intro");
+        let chorus = wave.add_region(1000, 2000, "Chorus");
+        wave.add_playlist_segment(intro, 1000, 1)?;
+        wave.add_playlist_segment(chorus, 2000, 4)?;
+        assert!(matches!(
+            wave.add_playlist_segment(99, 10, 1),
+            Err(WaverlyError::UnknownCuePoint(99))
+        ));
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let mut wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+        wave.validate_playlist()?;
+        let playlist = wave.playlist.as_ref().unwrap();
+        assert_eq!(playlist.num_segments, 2);
+        assert_eq!(playlist.segments[1].loops, 4);
+
+        wave.remove_cue(chorus);
+        assert!(matches!(
+            wave.validate_playlist(),
+            Err(WaverlyError::UnknownCuePoint(id)) if id == chorus
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_pcm() -> Result<()> {
+        let samples = [0i16, 1, -1, i16::MAX, i16::MIN, 0];
+        let wave = WaveBuilder::new(8000, 2, WaveFormat::Pcm, 16).build(&samples)?;
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+
+        let wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+        assert_eq!(wave.format.size, 16);
+        assert_eq!(wave.format.extension_size, None);
+        assert_eq!(wave.format.extensible, None);
+        assert_eq!(wave.samples::<i16>()?.collect::<Vec<_>>(), samples);
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_extensible_format() -> Result<()> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"RIFF\x44\0\0\0WAVEfmt \x28\0\0\0\xfe\xff\x02\0");
+        buf.extend_from_slice(b"\x80\xbb\0\0\0\xee\x02\0\x04\0\x10\0\x16\0\x10\0\x03\0\0\0");
+        buf.extend_from_slice(&SubFormat::Pcm.guid());
+        buf.extend_from_slice(b"data\x08\0\0\0\x01\0\xff\xff\0\x80\xff\x7f");
+
+        let wave = Wave::from_reader(Cursor::new(buf.clone()))?;
+        let format = &wave.format;
+        assert_eq!(format.audio_format, WaveFormat::Extensible);
+        assert_eq!(format.extension_size, Some(22));
+        let extensible = format.extensible.as_ref().unwrap();
+        assert_eq!(extensible.valid_bits_per_sample, 16);
+        assert_eq!(extensible.channel_mask, ChannelMask::STEREO);
+        assert_eq!(extensible.sub_format(), SubFormat::Pcm);
+        assert_eq!(format.effective_format(), WaveFormat::Pcm);
+        assert_eq!(
+            wave.samples::<i16>()?.collect::<Vec<_>>(),
+            [1, -1, i16::MIN, i16::MAX]
+        );
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_peaks_for_each_channel() -> Result<()> {
+        let samples = [0.5f32, -0.25, 0.0, 0.0, 0.0, -1.0];
+        let mut wave = WaveBuilder::new(8000, 6, WaveFormat::IeeeFloat, 32).build(&samples)?;
+        wave.update_peak(1_600_000_000)?;
+        let peak = wave.peak.as_ref().unwrap();
+        assert_eq!(peak.size, 56);
+        assert_eq!(peak.peaks[5].value, 1.0);
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let mut buf = virt_file.into_inner();
+        let wave = Wave::from_reader(Cursor::new(buf.clone()))?;
+        let peaks = &wave.peak.unwrap().peaks;
+        assert_eq!(peaks.len(), 6);
+        assert_eq!(peaks[0].value, 0.5);
+        assert_eq!(peaks[1].value, 0.25);
+
+        // Claim a single channel, leaving the PEAK chunk with too many peaks.
+        buf[22] = 1;
+        let strict = ParseOptions { strict: true };
+        assert!(Wave::from_reader_with_options(Cursor::new(&buf), strict).is_err());
+        let (wave, warnings) =
+            Wave::from_reader_with_options(Cursor::new(&buf), ParseOptions::default())?;
+        let peak = buf.windows(4).position(|id| id == b"PEAK").unwrap();
+        assert_eq!(
+            warnings,
+            [ParseWarning {
+                offset: peak as u64,
+                kind: ParseWarningKind::InvalidChunk { id: *b"PEAK" },
+            }]
+        );
+        assert!(wave.peak.is_none());
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(
+            virt_file.into_inner()[peak..peak + 64],
+            buf[peak..peak + 64]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_needs_channels_to_update_peaks() -> Result<()> {
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16).build(&[0i16; 2])?;
+        wave.format.num_channels = 0;
+        assert!(wave.update_peak(0).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_formats_it_cannot_decode() -> Result<()> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"RIFF\x8c\0\0\0WAVEfmt \x14\0\0\0\x31\0\x01\0\x40\x1f\0\0");
+        buf.extend_from_slice(b"\x59\x06\0\0\x41\0\0\0\x02\0\x40\x01fact\x04\0\0\0\x40\x01\0\0");
+        buf.extend_from_slice(b"LIST\x0e\0\0\0INFOINAM\x01\0\0\0a\0data\x41\0\0\0");
+        buf.extend_from_slice(&[0xAA; 65]);
+        buf.push(0);
+
+        let wave = Wave::from_reader(Cursor::new(buf.clone()))?;
+        assert_eq!(wave.format.audio_format, WaveFormat::Gsm610);
+        assert_eq!(wave.format.extension_size, Some(2));
+        assert_eq!(wave.format.extra, [0x40, 0x01]);
+        assert_eq!(wave.info_tag(InfoTag::Title).as_deref(), Some("a"));
+        assert!(matches!(
+            wave.samples::<i16>(),
+            Err(WaverlyError::UnsupportedFormat { .. })
+        ));
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+
+        // An unregistered tag, without the FACT chunk compressed formats should have.
+        buf[20..22].copy_from_slice(&0x1234u16.to_le_bytes());
+        buf[40..52].copy_from_slice(b"JUNK\x04\0\0\0\0\0\0\0");
+        let wave = Wave::from_reader(Cursor::new(buf))?;
+        assert_eq!(wave.format.audio_format, WaveFormat::Other(0x1234));
+        assert_eq!(wave.fact, None);
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_ima_adpcm() -> Result<()> {
+        let samples: Vec<i16> = (0..1000).map(|index| (index % 100) * 100 - 5000).collect();
+        let wave = WaveBuilder::new(22050, 1, WaveFormat::ImaAdpcm, 4).build(&samples)?;
+        assert_eq!(wave.format.block_align, 512);
+        assert_eq!(wave.format.extra, 1017u16.to_le_bytes());
+        assert_eq!(wave.format.byte_rate, 11100);
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+        assert_eq!(wave.fact.as_ref().map(|fact| fact.data), Some(1000));
+        let decoded: Vec<i16> = wave.samples()?.collect();
+        assert_eq!(decoded.len(), 1000);
+        assert_eq!(decoded[0], -5000);
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_ms_adpcm() -> Result<()> {
+        let samples: Vec<i16> = (0..1000).map(|index| (index % 100) * 100 - 5000).collect();
+        let wave = WaveBuilder::new(8000, 1, WaveFormat::MsAdpcm, 4).build(&samples)?;
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+        assert_eq!(wave.format.ms_adpcm(), Some(MsAdpcmFormat::standard(500)));
+        assert_eq!(wave.fact.as_ref().map(|fact| fact.data), Some(1000));
+        let decoded: Vec<i16> = wave.samples()?.collect();
+        assert_eq!(decoded.len(), 1000);
+        assert_eq!(decoded[..2], samples[..2]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_rf64() -> Result<()> {
+        let samples: Vec<i16> = (0..100).collect();
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16)
+            .container(Container::Rf64)
+            .build(&samples)?;
+        wave.unknown.push(UnknownChunk {
+            id: *b"JUNK",
+            data: vec![0; 6],
+        });
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        assert_eq!(buf.len(), 12 + 36 + 24 + 14 + 8 + 200);
+        assert_eq!(buf[..4], *b"RF64");
+        assert_eq!(buf[4..8], u32::MAX.to_le_bytes());
+        assert_eq!(buf[12..16], *b"ds64");
+        // The data chunk defers to the ds64 chunk for its size.
+        assert_eq!(buf[90..94], u32::MAX.to_le_bytes());
+
+        let wave = Wave::from_reader(Cursor::new(buf))?;
+        assert_eq!(wave.container, Container::Rf64);
+        let ds64 = wave.ds64.as_ref().unwrap();
+        assert_eq!(ds64.riff_size, 12 + 36 + 24 + 14 + 8 + 200 - 8);
+        assert_eq!(ds64.data_size, 200);
+        assert_eq!(ds64.sample_count, 100);
+        assert_eq!(wave.data.size, 200);
+        assert_eq!(wave.samples::<i16>()?.collect::<Vec<_>>(), samples);
+        assert_eq!(wave.unknown.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_rf64_chunk_sizes() -> Result<()> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"RF64\xFF\xFF\xFF\xFFWAVE");
+        buf.extend_from_slice(b"ds64");
+        buf.extend_from_slice(&40u32.to_le_bytes());
+        for size in [72u64, 4, 2] {
+            buf.extend_from_slice(&size.to_le_bytes());
+        }
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        buf.extend_from_slice(b"JUNK");
+        buf.extend_from_slice(&2u64.to_le_bytes());
+        buf.extend_from_slice(b"fmt \x10\x00\x00\x00\x01\x00\x01\x00");
+        buf.extend_from_slice(&[0x40, 0x1F, 0, 0, 0x80, 0x3E, 0, 0, 2, 0, 16, 0]);
+        buf.extend_from_slice(b"JUNK\xFF\xFF\xFF\xFF\x01\x02");
+        buf.extend_from_slice(b"data\xFF\xFF\xFF\xFF\x03\x00\x04\x00");
+
+        let wave = Wave::from_reader(Cursor::new(buf))?;
+        assert_eq!(wave.ds64.as_ref().map(|ds64| ds64.table.len()), Some(1));
+        assert_eq!(wave.unknown[0].data, [1, 2]);
+        assert_eq!(wave.samples::<i16>()?.collect::<Vec<_>>(), [3, 4]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_round_trips_bw64() -> Result<()> {
+        let mut wave = WaveBuilder::new(48000, 2, WaveFormat::Pcm, 24)
+            .container(Container::Bw64)
+            .build(&[0i32; 8])?;
+        let mut chna = ChnaChunk::default();
+        for track in 1..=2 {
+            chna.audio_ids.push(AudioId::new(
+                track,
+                &format!("ATU_0000000{track}"),
+                &format!("AT_0001000{track}_01"),
+                "AP_00010002",
+            )?);
+        }
+        wave.chna = Some(chna);
+        let axml = b"<ebuCoreMain><coreMetadata/></ebuCoreMain>".to_vec();
+        wave.axml = Some(axml.clone());
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        assert_eq!(buf[..4], *b"BW64");
+        assert_eq!(buf[12..16], *b"ds64");
+
+        let wave = Wave::from_reader(Cursor::new(buf))?;
+        assert_eq!(wave.container, Container::Bw64);
+        let chna = wave.chna.as_ref().unwrap();
+        assert_eq!((chna.num_tracks, chna.num_uids, chna.size), (2, 2, 84));
+        assert_eq!(chna.audio_ids[1].uid(), "ATU_00000002");
+        assert_eq!(chna.audio_ids[1].track_ref(), "AT_00010002_01");
+        assert_eq!(wave.axml, Some(axml));
+        assert_eq!(wave.data.size, 24);
+        assert_eq!(
+            wave.order,
+            [
+                ChunkKind::Format,
+                ChunkKind::Chna,
+                ChunkKind::Axml,
+                ChunkKind::Data
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_bext() -> Result<()> {
+        let mut wave = WaveBuilder::new(48000, 1, WaveFormat::Pcm, 16).build(&[0i16; 4])?;
+        let mut bext = BextChunk::default();
+        bext.description = "Interview".into();
+        bext.origination_date = "2024-05-01".into();
+        bext.origination_time = "09:00:00".into();
+        bext.time_reference = 48000 * 3600 * 9;
+        bext.coding_history = "A=PCM,F=48000,W=16,M=mono\r\n".into();
+        wave.bext = Some(bext);
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        // The odd sized coding history is followed by a pad byte.
+        assert_eq!(buf.len(), 12 + 24 + 8 + 602 + 27 + 1 + 8 + 8);
+
+        let mut wave = Wave::from_reader(Cursor::new(buf))?;
+        let bext = wave.bext.as_mut().unwrap();
+        assert_eq!(bext.description, "Interview");
+        assert_eq!(bext.time_reference, 1_555_200_000);
+        assert_eq!(bext.coding_history, "A=PCM,F=48000,W=16,M=mono\r\n");
+        assert_eq!(wave.order[1], ChunkKind::Bext);
+
+        wave.bext.as_mut().unwrap().origination_time = "09:00:00Z".into();
+        assert!(wave.write(Cursor::new(Vec::new())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_cart() -> Result<()> {
+        let mut wave = WaveBuilder::new(48000, 1, WaveFormat::Pcm, 16).build(&[0i16; 4])?;
+        let mut cart = CartChunk::default();
+        cart.title = "Station ID".into();
+        cart.cut_id = "0042".into();
+        cart.start_date = "2024-05-01".into();
+        cart.start_time = "06:00:00".into();
+        cart.tag_text = "Top of the hour\r\n".into();
+        wave.cart = Some(cart);
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        // The odd sized tag text is followed by a pad byte.
+        assert_eq!(buf.len(), 12 + 24 + 8 + 2048 + 17 + 1 + 8 + 8);
+
+        let mut wave = Wave::from_reader(Cursor::new(buf))?;
+        let cart = wave.cart.as_ref().unwrap();
+        assert_eq!(cart.title, "Station ID");
+        assert_eq!(cart.cut_id, "0042");
+        assert_eq!(cart.tag_text, "Top of the hour\r\n");
+        assert_eq!(wave.order[1], ChunkKind::Cart);
+
+        wave.cart.as_mut().unwrap().start_date = "1st of May 2024".into();
+        assert!(wave.write(Cursor::new(Vec::new())).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_ixml() -> Result<()> {
+        let mut wave = WaveBuilder::new(48000, 1, WaveFormat::Pcm, 16).build(&[0i16; 4])?;
+        let mut ixml = IxmlChunk::default();
+        ixml.set_project("Pilot");
+        ixml.set_take("7");
+        ixml.padding = 15;
+        wave.ixml = Some(ixml);
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let mut wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+        let ixml = wave.ixml.as_mut().unwrap();
+        assert_eq!(ixml.project().as_deref(), Some("Pilot"));
+        assert_eq!(ixml.padding, 15);
+        ixml.set_take("8");
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+        assert_eq!(wave.ixml.unwrap().take().as_deref(), Some("8"));
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_sampler_and_instrument() -> Result<()> {
+        let mut wave = WaveBuilder::new(44100, 1, WaveFormat::Pcm, 16).build(&[0i16; 100])?;
+        wave.sampler = Some(SamplerChunk {
+            sample_period: 22675,
+            midi_unity_note: 57,
+            loops: vec![SampleLoop {
+                cue_point_id: 0,
+                loop_type: LoopType::Alternating,
+                start: 10,
+                end: 89,
+                fraction: 0,
+                play_count: 0,
+            }],
+            sampler_data: vec![1, 2, 3],
+            ..SamplerChunk::default()
+        });
+        wave.instrument = Some(InstrumentChunk {
+            unshifted_note: 57,
+            fine_tune: -12,
+            low_note: 55,
+            high_note: 59,
+            ..InstrumentChunk::default()
+        });
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        // Both chunks are odd sized, and followed by a pad byte.
+        assert_eq!(buf.len(), 12 + 24 + 8 + 63 + 1 + 8 + 7 + 1 + 8 + 200);
+
+        let wave = Wave::from_reader(Cursor::new(buf))?;
+        let sampler = wave.sampler.as_ref().unwrap();
+        assert_eq!(sampler.size, 63);
+        assert_eq!(sampler.loops[0].loop_type, LoopType::Alternating);
+        assert_eq!(sampler.loops[0].end, 89);
+        assert_eq!(sampler.sampler_data, [1, 2, 3]);
+        let instrument = wave.instrument.as_ref().unwrap();
+        assert_eq!(instrument.fine_tune, -12);
+        assert_eq!((instrument.low_note, instrument.high_note), (55, 59));
+        assert_eq!(wave.data.size, 200);
+        Ok(())
+    }
+
+    #[test]
+    fn it_keeps_sampler_bytes_the_counts_do_not_cover() -> Result<()> {
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16).build(&[0i16; 2])?;
+        // No loops and no sampler data, followed by 8 bytes.
+        let mut smpl = vec![0; 44];
+        smpl[36..].copy_from_slice(&[1; 8]);
+        wave.unknown.push(UnknownChunk {
+            id: *b"smpl",
+            data: smpl.clone(),
+        });
+        // 7 bytes of instrument settings and one more.
+        wave.unknown.push(UnknownChunk {
+            id: *b"inst",
+            data: vec![60, 0, 0, 0, 127, 1, 127, 0],
+        });
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+
+        let (wave, warnings) =
+            Wave::from_reader_with_options(Cursor::new(&buf), ParseOptions::default())?;
+        assert_eq!(wave.sampler.as_ref().unwrap().trailing, [1; 8]);
+        assert!(wave.instrument.is_none());
+        let inst = buf.windows(4).position(|id| id == b"inst").unwrap();
+        assert_eq!(
+            warnings,
+            [ParseWarning {
+                offset: inst as u64,
+                kind: ParseWarningKind::InvalidChunk { id: *b"inst" },
+            }]
+        );
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+
+        // Sampler data that runs past the end of the chunk.
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16).build(&[0i16; 2])?;
+        smpl[32] = 100;
+        wave.unknown.push(UnknownChunk {
+            id: *b"smpl",
+            data: smpl,
+        });
+        wave.unknown.push(UnknownChunk {
+            id: *b"JUNK",
+            data: vec![0; 100],
+        });
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+
+        let (wave, warnings) =
+            Wave::from_reader_with_options(Cursor::new(&buf), ParseOptions::default())?;
+        assert!(wave.sampler.is_none());
+        assert_eq!(warnings.len(), 1);
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_acid_loops() -> Result<()> {
+        let mut wave = WaveBuilder::new(44100, 2, WaveFormat::Pcm, 16).build(&[0i16; 8])?;
+        let mut acid = AcidChunk::new(92.5, 16);
+        acid.set_root(62);
+        wave.acid = Some(acid);
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+        assert_eq!(buf.len(), 12 + 24 + 8 + 24 + 8 + 16);
+        assert_eq!(buf[36..44], [b'a', b'c', b'i', b'd', 24, 0, 0, 0]);
+        assert_eq!(buf[44..48], [0x6, 0, 0, 0]);
+
+        let wave = Wave::from_reader(Cursor::new(&buf))?;
+        let acid = wave.acid.unwrap();
+        assert_eq!(acid.tempo, 92.5);
+        assert_eq!(acid.num_beats, 16);
+        assert_eq!((acid.meter_numerator, acid.meter_denominator), (4, 4));
+        assert_eq!(acid.root(), Some(62));
+        assert!(acid.flags.contains(AcidFlags::STRETCH));
+        assert!(!acid.flags.contains(AcidFlags::ONE_SHOT));
+
+        // Leave out the tempo.
+        let mut buf = [&buf[..40], b"\x14", &buf[41..64], &buf[68..]].concat();
+        let riff_size = buf.len() as u32 - 8;
+        buf[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        let (wave, warnings) =
+            Wave::from_reader_with_options(Cursor::new(&buf), ParseOptions::default())?;
+        assert_eq!(
+            warnings,
+            [ParseWarning {
+                offset: 36,
+                kind: ParseWarningKind::InvalidChunk { id: *b"acid" },
+            }]
+        );
+        assert!(wave.acid.is_none());
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+        Ok(())
+    }
+
+    #[test]
+    fn it_writes_id3_tags() -> Result<()> {
+        let mut wave = WaveBuilder::new(44100, 2, WaveFormat::Pcm, 16).build(&[0i16; 8])?;
+        wave.set_info_tag(InfoTag::Title, "Title");
+        let mut tag = Id3Chunk::new();
+        tag.set_text(*b"TIT2", "Title");
+        tag.set_text(*b"TPE1", "Artist");
+        wave.id3 = Some(tag);
+        wave.unknown.push(UnknownChunk {
+            id: *b"ID3 ",
+            data: b"ID3\x02\x00\x00\x00\x00\x00\x00".to_vec(),
+        });
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let wave = Wave::from_reader(Cursor::new(virt_file.into_inner()))?;
+        let tag = wave.id3.as_ref().unwrap();
+        assert_eq!(tag.id, *b"id3 ");
+        assert_eq!(tag.text(b"TPE1"), Some("Artist"));
+        assert_eq!(wave.info_tag(InfoTag::Title).as_deref(), Some("Title"));
+        // Later tags are kept as is.
+        assert_eq!(wave.unknown[0].id, *b"ID3 ");
+        Ok(())
+    }
+
+    #[test]
+    fn it_recovers_from_misaligned_chunks() -> Result<()> {
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16).build(&[1i16, 2, 3])?;
+        wave.unknown.push(UnknownChunk {
+            id: *b"minf",
+            data: vec![1, 2, 3],
+        });
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let mut buf = virt_file.into_inner();
+        let minf = buf.windows(4).position(|id| id == b"minf").unwrap() as u64;
+
+        // Drop the pad byte, keeping the RIFF size in step.
+        buf.remove(minf as usize + 11);
+        let riff_size = buf.len() as u32 - 8;
+        buf[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        let missing_pad = ParseWarning {
+            offset: minf + 11,
+            kind: ParseWarningKind::MissingPadByte { id: *b"minf" },
+        };
+        let strict = ParseOptions { strict: true };
+        match Wave::from_reader_with_options(Cursor::new(&buf), strict) {
+            Err(WaverlyError::Malformed(warning)) => assert_eq!(warning, missing_pad),
+            result => panic!("expected a malformed file, got {result:?}"),
+        }
+        let lenient = ParseOptions::default();
+        let (wave, warnings) = Wave::from_reader_with_options(Cursor::new(&buf), lenient)?;
+        assert_eq!(warnings, core::slice::from_ref(&missing_pad));
+        assert_eq!(wave.unknown[0].data, [1, 2, 3]);
+        assert_eq!(wave.samples::<i16>()?.collect::<Vec<_>>(), [1, 2, 3]);
+
+        // Cut the data chunk short.
+        buf.truncate(buf.len() - 3);
+        let (wave, warnings) = Wave::from_reader_with_options(Cursor::new(&buf), lenient)?;
+        let data = minf + 11;
+        assert_eq!(
+            warnings,
+            [
+                ParseWarning {
+                    offset: 4,
+                    kind: ParseWarningKind::RiffSize {
+                        stored: riff_size as u64,
+                        actual: riff_size as u64 - 3,
+                    },
+                },
+                missing_pad,
+                ParseWarning {
+                    offset: data,
+                    kind: ParseWarningKind::TruncatedChunk {
+                        id: *b"data",
+                        size: 6,
+                        available: 3,
+                    },
+                },
+            ]
+        );
+        assert_eq!(wave.data.data, [1, 0, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_recovers_from_bogus_sizes() -> Result<()> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        // An extension size of 22 in a format chunk that leaves room for none.
+        buf.extend_from_slice(
+            b"fmt \x12\0\0\0\x01\0\x01\0\x40\x1f\0\0\x80\x3e\0\0\x02\0\x10\0\x16\0",
+        );
+        buf.extend_from_slice(b"data\x04\0\0\0\x01\0\x02\0");
+        let riff_size = buf.len() as u32 - 8;
+        buf[4..8].copy_from_slice(&riff_size.to_le_bytes());
+        buf.extend_from_slice(b"\0\x01\x02\x03\x04\x05\x06\x07\x08");
+
+        let strict = ParseOptions { strict: true };
+        match Wave::from_reader_with_options(Cursor::new(&buf), strict) {
+            Err(WaverlyError::Malformed(ParseWarning { offset: 4, .. })) => {}
+            result => panic!("expected a malformed file, got {result:?}"),
+        }
+        let (wave, warnings) =
+            Wave::from_reader_with_options(Cursor::new(&buf), ParseOptions::default())?;
+        assert_eq!(
+            warnings,
+            [
+                ParseWarning {
+                    offset: 4,
+                    kind: ParseWarningKind::RiffSize {
+                        stored: riff_size as u64,
+                        actual: riff_size as u64 + 9,
+                    },
+                },
+                ParseWarning {
+                    offset: 36,
+                    kind: ParseWarningKind::ExtensionSize {
+                        stored: 22,
+                        actual: 0,
+                    },
+                },
+                ParseWarning {
+                    offset: 50,
+                    kind: ParseWarningKind::TrailingBytes { len: 9 },
+                },
+            ]
+        );
+        assert_eq!(wave.format.extension_size, Some(0));
+        assert_eq!(wave.samples::<i16>()?.collect::<Vec<_>>(), [1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_reads_a_final_data_chunk_without_pad_byte() -> Result<()> {
+        let wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 8).build(&[-256i16, 0, 256])?;
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let mut buf = virt_file.into_inner();
+        buf.pop();
+
+        let wave = Wave::from_reader(Cursor::new(&buf))?;
+        assert_eq!(wave.data.data, [127, 128, 129]);
+        let strict = ParseOptions { strict: true };
+        assert!(Wave::from_reader_with_options(Cursor::new(&buf), strict).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn it_keeps_repeated_chunks() -> Result<()> {
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16).build(&[0i16; 2])?;
+        wave.sampler = Some(SamplerChunk {
+            midi_unity_note: 60,
+            ..SamplerChunk::default()
+        });
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+
+        // Repeat the smpl chunk, with a different unity note.
+        let smpl = buf.windows(4).position(|id| id == b"smpl").unwrap();
+        let mut repeated = buf[smpl..smpl + 44].to_vec();
+        repeated[8 + 12] = 64;
+        let mut buf = [&buf[..smpl + 44], &repeated, &buf[smpl + 44..]].concat();
+        let riff_size = buf.len() as u32 - 8;
+        buf[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+        let (wave, warnings) =
+            Wave::from_reader_with_options(Cursor::new(&buf), ParseOptions::default())?;
+        assert_eq!(
+            warnings,
+            [ParseWarning {
+                offset: smpl as u64 + 44,
+                kind: ParseWarningKind::DuplicateChunk { id: *b"smpl" },
+            }]
+        );
+        assert_eq!(wave.sampler.as_ref().unwrap().midi_unity_note, 60);
+        assert_eq!(wave.unknown[0].id, *b"smpl");
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+        Ok(())
+    }
+
+    #[test]
+    fn it_keeps_chunks_that_cannot_be_parsed() -> Result<()> {
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16).build(&[0i16; 2])?;
+        wave.unknown.push(UnknownChunk {
+            id: *b"bext",
+            data: b"Too short".to_vec(),
+        });
+        wave.unknown.push(UnknownChunk {
+            id: *b"cart",
+            data: b"0101".to_vec(),
+        });
+        wave.unknown.push(UnknownChunk {
+            id: *b"iXML",
+            data: b"<BWFXML>\xFF</BWFXML>".to_vec(),
+        });
+        wave.unknown.push(UnknownChunk {
+            id: *b"id3 ",
+            data: b"ID3\x05".to_vec(),
+        });
+        // Two cue points and three segments, with room for one of each.
+        let mut cue = b"\x02\0\0\0\x01\0\0\0".to_vec();
+        cue.extend_from_slice(&[0; 20]);
+        wave.unknown.push(UnknownChunk {
+            id: *b"cue ",
+            data: cue,
+        });
+        wave.unknown.push(UnknownChunk {
+            id: *b"plst",
+            data: b"\x03\0\0\0\x01\0\0\0\x10\0\0\0\x01\0\0\0".to_vec(),
+        });
+        // One entry and 2 bytes more, then too short for the counts.
+        wave.unknown.push(UnknownChunk {
+            id: *b"chna",
+            data: [0; 4 + 40 + 2].to_vec(),
+        });
+        wave.unknown.push(UnknownChunk {
+            id: *b"chna",
+            data: vec![1, 0],
+        });
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+
+        let (wave, warnings) =
+            Wave::from_reader_with_options(Cursor::new(&buf), ParseOptions::default())?;
+        let ids: Vec<_> = warnings
+            .iter()
+            .map(|warning| match warning.kind {
+                ParseWarningKind::InvalidChunk { id } => id,
+                _ => panic!("unexpected warning {warning:?}"),
+            })
+            .collect();
+        assert_eq!(
+            ids,
+            [*b"bext", *b"cart", *b"iXML", *b"id3 ", *b"cue ", *b"plst", *b"chna", *b"chna"]
+        );
+        assert!(wave.bext.is_none() && wave.cart.is_none() && wave.ixml.is_none());
+        assert!(wave.id3.is_none());
+        assert!(wave.cue.is_none() && wave.playlist.is_none());
+
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+        Ok(())
+    }
+
+    #[test]
+    fn it_round_trips_latin1_bext() -> Result<()> {
+        let mut wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16).build(&[0i16; 2])?;
+        let mut bext = vec![0; 602];
+        bext[..5].copy_from_slice(b"Caf\xe9!");
+        bext[500] = 0x5A;
+        wave.unknown.push(UnknownChunk {
+            id: *b"bext",
+            data: bext,
+        });
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        let buf = virt_file.into_inner();
+
+        let wave = Wave::from_reader(Cursor::new(&buf))?;
+        assert_eq!(wave.bext.as_ref().unwrap().description, "Café!");
+        let mut virt_file = Cursor::new(Vec::new());
+        wave.write(&mut virt_file)?;
+        assert_eq!(virt_file.into_inner(), buf);
+        Ok(())
+    }
 }
@@ -0,0 +1,60 @@
+//! Reading and writing the sub-chunks of LIST chunks.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+/// Iterator over the id and body of each sub-chunk in the body of a LIST chunk.
+pub(crate) struct SubChunks<'a> {
+    data: &'a [u8],
+}
+
+pub(crate) fn sub_chunks(data: &[u8]) -> SubChunks<'_> {
+    SubChunks { data }
+}
+
+impl<'a> SubChunks<'a> {
+    /// The bytes not yet read, which once iteration ends are too short to hold a sub-chunk.
+    pub(crate) fn rest(&self) -> &'a [u8] {
+        self.data
+    }
+}
+
+impl<'a> Iterator for SubChunks<'a> {
+    type Item = ([u8; 4], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.len() < 8 {
+            return None;
+        }
+        let id = [self.data[0], self.data[1], self.data[2], self.data[3]];
+        let size = u32::from_le_bytes([self.data[4], self.data[5], self.data[6], self.data[7]]);
+        let end = self.data.len().min(8 + size as usize);
+        let (body, mut rest) = (&self.data[8..end], &self.data[end..]);
+
+        // Some writers leave out the pad byte after odd sized sub-chunks. Ids never start with
+        // NUL, so only a NUL is taken to be padding.
+        if size % 2 == 1 && rest.first() == Some(&0) {
+            rest = &rest[1..];
+        }
+        self.data = rest;
+        Some((id, body))
+    }
+}
+
+/// Appends a sub-chunk, followed by a pad byte if its body has an odd length.
+pub(crate) fn push_sub_chunk(out: &mut Vec<u8>, id: [u8; 4], data: &[u8]) {
+    out.extend_from_slice(&id);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Encodes a string with a NUL terminator.
+pub(crate) fn to_zstr(value: &str) -> Vec<u8> {
+    let mut data = Vec::with_capacity(value.len() + 1);
+    data.extend_from_slice(value.as_bytes());
+    data.push(0);
+    data
+}
@@ -0,0 +1,92 @@
+//! The playlist chunk, which describes a play order for sections of the audio.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use binrw::{binrw, BinReaderExt};
+
+use crate::CueChunk;
+
+/// Sections of audio, each starting at a cue point, to be played in order.
+#[binrw]
+#[brw(magic = b"plst")]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PlaylistChunk {
+    #[br(little)]
+    pub size: u32,
+    #[br(little)]
+    pub num_segments: u32,
+    #[br(count = num_segments)]
+    pub segments: Vec<Segment>,
+}
+
+impl PlaylistChunk {
+    /// Parses a playlist chunk, including its id and size. Returns `None` unless the segments
+    /// fill the chunk exactly.
+    pub(crate) fn parse(chunk: &[u8]) -> Option<PlaylistChunk> {
+        let count = u32::from_le_bytes(chunk.get(8..12)?.try_into().ok()?);
+        if chunk.len() as u64 != 12 + 12 * count as u64 {
+            return None;
+        }
+        crate::parse_whole(chunk, |cursor| cursor.read_le())
+    }
+
+    /// Ids of cue points that segments refer to, but that are not in `cue`.
+    pub fn missing_cue_ids<'a>(
+        &'a self,
+        cue: Option<&'a CueChunk>,
+    ) -> impl Iterator<Item = u32> + 'a {
+        self.segments
+            .iter()
+            .map(|segment| segment.cue_id)
+            .filter(move |&id| cue.and_then(|cue| cue.get(id)).is_none())
+    }
+
+    /// Updates `size` and `num_segments` to match `segments`.
+    pub(crate) fn update_size(&mut self) {
+        self.num_segments = self.segments.len() as u32;
+        self.size = 4 + 12 * self.num_segments;
+    }
+}
+
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    /// The cue point the segment starts at.
+    #[br(little)]
+    pub cue_id: u32,
+    /// Length of the segment in sample frames.
+    #[br(little)]
+    pub length: u32,
+    /// Number of times the segment is played.
+    #[br(little)]
+    pub loops: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CuePoint;
+
+    #[test]
+    fn it_reports_missing_cue_points() {
+        let mut cue = CueChunk::default();
+        cue.cue_points.push(CuePoint::new(1, 0));
+        let segment = |cue_id| Segment {
+            cue_id,
+            length: 10,
+            loops: 1,
+        };
+        let playlist = PlaylistChunk {
+            size: 0,
+            num_segments: 0,
+            segments: [segment(1), segment(2), segment(1), segment(3)].to_vec(),
+        };
+
+        assert_eq!(
+            playlist.missing_cue_ids(Some(&cue)).collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(playlist.missing_cue_ids(None).count(), 4);
+    }
+}
@@ -0,0 +1,239 @@
+//! Streaming access to the data chunk, for files too large to load into memory.
+
+use core::marker::PhantomData;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::sample::Encoding;
+use crate::{io, FormatChunk, ParseOptions, ParseWarning, Result, Sample, Wave};
+
+/// Number of samples [`StreamSamples`] reads from the data chunk at a time.
+const BUFFER_SAMPLES: usize = 4096;
+
+/// Reads the chunks of a WAV file, leaving the body of the data chunk to be streamed.
+///
+/// The reader itself implements [`io::Read`] over the body of the data chunk, starting at its
+/// first byte and ending at its last, or at the end of the file if the data chunk is truncated.
+#[derive(Debug)]
+pub struct WaveReader<R> {
+    reader: R,
+    wave: Wave,
+    data_offset: u64,
+    data_len: u64,
+    /// Position within the body of the data chunk.
+    position: u64,
+}
+
+impl<R: io::Read + io::Seek> WaveReader<R> {
+    /// Reads every chunk but the body of the data chunk, then positions the reader at the
+    /// start of the audio.
+    pub fn new(reader: R) -> Result<Self> {
+        Ok(WaveReader::with_options(reader, ParseOptions::default())?.0)
+    }
+
+    /// Like [`WaveReader::new`], along with the problems that were worked around. See
+    /// [`Wave::from_reader_with_options`].
+    pub fn with_options(mut reader: R, options: ParseOptions) -> Result<(Self, Vec<ParseWarning>)> {
+        let (wave, data_offset, warnings) = Wave::read_chunks(&mut reader, false, options)?;
+        let end = reader.seek(io::SeekFrom::End(0))?;
+        let data_len = wave.data.size.min(end.saturating_sub(data_offset));
+        reader.seek(io::SeekFrom::Start(data_offset))?;
+        let reader = WaveReader {
+            reader,
+            wave,
+            data_offset,
+            data_len,
+            position: 0,
+        };
+        Ok((reader, warnings))
+    }
+
+    /// The chunks of the file. The body of the data chunk is left empty, while its size is the
+    /// one stored in the file.
+    pub fn wave(&self) -> &Wave {
+        &self.wave
+    }
+
+    pub fn format(&self) -> &FormatChunk {
+        &self.wave.format
+    }
+
+    /// Offset of the body of the data chunk from the start of the file.
+    pub fn data_offset(&self) -> u64 {
+        self.data_offset
+    }
+
+    /// Number of bytes of audio that can be read.
+    pub fn data_len(&self) -> u64 {
+        self.data_len
+    }
+
+    /// Number of bytes of a sample frame. Fails for block based and unsupported formats.
+    fn frame_size(&self) -> Result<u64> {
+        let encoding = Encoding::from_format(&self.wave.format)?;
+        Ok(encoding.bytes_per_sample() as u64 * self.wave.format.num_channels as u64)
+    }
+
+    /// Number of whole sample frames in the data chunk.
+    pub fn num_frames(&self) -> Result<u64> {
+        Ok(self.data_len.checked_div(self.frame_size()?).unwrap_or(0))
+    }
+
+    /// Moves to the start of a sample frame, or to the end of the audio if `frame` is past it.
+    pub fn seek_frame(&mut self, frame: u64) -> Result<()> {
+        let position = frame.saturating_mul(self.frame_size()?).min(self.data_len);
+        self.reader
+            .seek(io::SeekFrom::Start(self.data_offset + position))?;
+        self.position = position;
+        Ok(())
+    }
+
+    /// Decodes samples of type `S` from the current position, interleaved by channel. Supports
+    /// the same formats as [`Wave::samples`], except for block based formats.
+    pub fn samples<S: Sample>(&mut self) -> Result<StreamSamples<'_, R, S>> {
+        let encoding = Encoding::from_format(&self.wave.format)?;
+        Ok(StreamSamples {
+            reader: self,
+            encoding,
+            buffer: Vec::new(),
+            offset: 0,
+            sample: PhantomData,
+        })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: io::Read + io::Seek> io::Read for WaveReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.data_len - self.position;
+        let len = buf.len().min(remaining.try_into().unwrap_or(usize::MAX));
+        let read = self.reader.read(&mut buf[..len])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+/// Iterator over the samples of a [`WaveReader`], reading the data chunk as it goes.
+///
+/// Created by [`WaveReader::samples`]. A trailing partial sample is ignored.
+#[derive(Debug)]
+pub struct StreamSamples<'a, R, S> {
+    reader: &'a mut WaveReader<R>,
+    encoding: Encoding,
+    buffer: Vec<u8>,
+    /// Position of the next sample within `buffer`.
+    offset: usize,
+    sample: PhantomData<S>,
+}
+
+impl<'a, R: io::Read + io::Seek, S: Sample> StreamSamples<'a, R, S> {
+    fn fill(&mut self) -> io::Result<()> {
+        let bytes = self.encoding.bytes_per_sample();
+        let remaining = self.reader.data_len - self.reader.position;
+        let len = remaining.min((BUFFER_SAMPLES * bytes) as u64) as usize / bytes * bytes;
+        self.buffer.resize(len, 0);
+        self.offset = 0;
+        io::Read::read_exact(self.reader, &mut self.buffer)
+    }
+}
+
+impl<'a, R: io::Read + io::Seek, S: Sample> Iterator for StreamSamples<'a, R, S> {
+    type Item = Result<S>;
+
+    fn next(&mut self) -> Option<Result<S>> {
+        let bytes = self.encoding.bytes_per_sample();
+        if self.offset + bytes > self.buffer.len() {
+            if let Err(error) = self.fill() {
+                self.buffer.clear();
+                return Some(Err(error.into()));
+            }
+            if self.buffer.is_empty() {
+                return None;
+            }
+        }
+        let sample = self
+            .encoding
+            .decode(&self.buffer[self.offset..self.offset + bytes]);
+        self.offset += bytes;
+        Some(Ok(sample))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let bytes = self.encoding.bytes_per_sample() as u64;
+        let buffered = (self.buffer.len() - self.offset) as u64 / bytes;
+        let remaining = (self.reader.data_len - self.reader.position) / bytes;
+        let len = (buffered + remaining).try_into().unwrap_or(usize::MAX);
+        (len, Some(len))
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::{WaveBuilder, WaveFormat};
+    use std::fs::File;
+    use std::io::{Cursor, Read};
+    use std::vec::Vec;
+
+    #[test]
+    fn it_streams_the_data_chunk() -> Result<()> {
+        let wave = Wave::from_reader(File::open("./meta/16bit-2ch-float-peak.wav")?)?;
+        let mut reader = WaveReader::new(File::open("./meta/16bit-2ch-float-peak.wav")?)?;
+        assert_eq!(reader.data_offset(), 88);
+        assert_eq!(reader.data_len(), wave.data.size);
+        assert_eq!(reader.num_frames()?, wave.data.size / 16);
+        assert_eq!(reader.wave().peak, wave.peak);
+        assert!(reader.wave().data.data.is_empty());
+
+        let streamed = reader.samples::<f64>()?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(streamed, wave.samples::<f64>()?.collect::<Vec<_>>());
+
+        reader.seek_frame(1000)?;
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        assert_eq!(bytes, wave.data.data[16 * 1000..]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_seeks_by_frame() -> Result<()> {
+        let samples: Vec<i16> = (0..20_000).collect();
+        let wave = WaveBuilder::new(8000, 2, WaveFormat::Pcm, 16).build(&samples)?;
+        let mut file = Cursor::new(Vec::new());
+        wave.write(&mut file)?;
+
+        let mut reader = WaveReader::new(Cursor::new(file.into_inner()))?;
+        reader.seek_frame(9_000)?;
+        let mut samples = reader.samples::<i16>()?;
+        assert_eq!(samples.size_hint(), (2_000, Some(2_000)));
+        assert_eq!(samples.next().transpose()?, Some(18_000));
+        assert_eq!(samples.count(), 1_999);
+
+        reader.seek_frame(20_000)?;
+        assert_eq!(reader.samples::<i16>()?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn it_stops_at_a_truncated_data_chunk() -> Result<()> {
+        let wave = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 16).build(&[1i16, 2, 3])?;
+        let mut file = Cursor::new(Vec::new());
+        wave.write(&mut file)?;
+        let mut bytes = file.into_inner();
+        bytes.truncate(bytes.len() - 3);
+
+        let strict = ParseOptions { strict: true };
+        assert!(WaveReader::with_options(Cursor::new(&bytes), strict).is_err());
+        let mut reader = WaveReader::new(Cursor::new(bytes))?;
+        assert_eq!(reader.data_len(), 3);
+        let samples = reader.samples::<i16>()?.collect::<Result<Vec<_>>>()?;
+        assert_eq!(samples, [1]);
+        Ok(())
+    }
+}
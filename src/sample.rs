@@ -0,0 +1,513 @@
+//! Conversion between the raw bytes of a data chunk and typed samples.
+
+use core::marker::PhantomData;
+use core::slice::ChunksExact;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::adpcm;
+use crate::{FormatChunk, Result, WaveFormat, WaverlyError};
+
+/// A type that samples can be decoded into and encoded from.
+///
+/// Integer samples are scaled so that full scale in the source format is full scale in the
+/// target type, e.g. an 8-bit sample of `0xFF` becomes `0x7F00` as an `i16`. Floating point
+/// samples are normalized to the range `-1.0..=1.0`; converting a float to an integer type
+/// saturates values outside of that range.
+pub trait Sample: Copy {
+    /// Converts a signed integer sample with `bits` significant bits.
+    fn from_int(value: i32, bits: u32) -> Self;
+
+    /// Converts a floating point sample in the range `-1.0..=1.0`.
+    fn from_float(value: f64) -> Self;
+
+    /// Converts to a signed integer sample with `bits` significant bits.
+    fn to_int(self, bits: u32) -> i32;
+
+    /// Converts to a floating point sample in the range `-1.0..=1.0`.
+    fn to_float(self) -> f64;
+}
+
+impl Sample for i16 {
+    fn from_int(value: i32, bits: u32) -> Self {
+        if bits >= 16 {
+            (value >> (bits - 16)) as i16
+        } else {
+            (value << (16 - bits)) as i16
+        }
+    }
+
+    fn from_float(value: f64) -> Self {
+        (value * 32768.0) as i16
+    }
+
+    fn to_int(self, bits: u32) -> i32 {
+        if bits >= 16 {
+            (self as i32) << (bits - 16)
+        } else {
+            self as i32 >> (16 - bits)
+        }
+    }
+
+    fn to_float(self) -> f64 {
+        self as f64 / 32768.0
+    }
+}
+
+impl Sample for i32 {
+    fn from_int(value: i32, bits: u32) -> Self {
+        value << (32 - bits)
+    }
+
+    fn from_float(value: f64) -> Self {
+        (value * 2147483648.0) as i32
+    }
+
+    fn to_int(self, bits: u32) -> i32 {
+        self >> (32 - bits)
+    }
+
+    fn to_float(self) -> f64 {
+        self as f64 / 2147483648.0
+    }
+}
+
+impl Sample for f32 {
+    fn from_int(value: i32, bits: u32) -> Self {
+        f64::from_int(value, bits) as f32
+    }
+
+    fn from_float(value: f64) -> Self {
+        value as f32
+    }
+
+    fn to_int(self, bits: u32) -> i32 {
+        (self as f64).to_int(bits)
+    }
+
+    fn to_float(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Sample for f64 {
+    fn from_int(value: i32, bits: u32) -> Self {
+        value as f64 / (1u64 << (bits - 1)) as f64
+    }
+
+    fn from_float(value: f64) -> Self {
+        value
+    }
+
+    fn to_int(self, bits: u32) -> i32 {
+        let scale = (1u64 << (bits - 1)) as f64;
+        (self * scale).clamp(-scale, scale - 1.0) as i32
+    }
+
+    fn to_float(self) -> f64 {
+        self
+    }
+}
+
+/// How a single sample is laid out in the data chunk.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Encoding {
+    /// 8-bit PCM, which unlike every other PCM width is unsigned, with the given number of valid
+    /// bits.
+    Unsigned8(u32),
+    /// Little-endian two's complement PCM with a container of `bytes` bytes, of which the most
+    /// significant `bits` bits are valid.
+    Signed {
+        bytes: usize,
+        bits: u32,
+    },
+    Float32,
+    Float64,
+    ALaw,
+    MuLaw,
+}
+
+impl Encoding {
+    pub(crate) fn from_format(format: &FormatChunk) -> Result<Self> {
+        let effective_format = format.effective_format();
+        if let WaveFormat::Pcm | WaveFormat::IeeeFloat = effective_format {
+            format.validate_bit_depth()?;
+        }
+        let bits = format.valid_bits() as u32;
+        let encoding = match (effective_format, format.container_bits()) {
+            (WaveFormat::Pcm, 8) => Encoding::Unsigned8(bits),
+            (WaveFormat::Pcm, container @ (16 | 24 | 32)) => Encoding::Signed {
+                bytes: container as usize / 8,
+                bits,
+            },
+            (WaveFormat::IeeeFloat, 32) => Encoding::Float32,
+            (WaveFormat::IeeeFloat, 64) => Encoding::Float64,
+            (WaveFormat::Alaw, 8) => Encoding::ALaw,
+            (WaveFormat::Mulaw, 8) => Encoding::MuLaw,
+            _ => {
+                return Err(WaverlyError::UnsupportedFormat {
+                    format: format.audio_format,
+                    bits_per_sample: format.bits_per_sample,
+                })
+            }
+        };
+        Ok(encoding)
+    }
+
+    pub(crate) fn bytes_per_sample(self) -> usize {
+        match self {
+            Encoding::Unsigned8(_) | Encoding::ALaw | Encoding::MuLaw => 1,
+            Encoding::Signed { bytes, .. } => bytes,
+            Encoding::Float32 => 4,
+            Encoding::Float64 => 8,
+        }
+    }
+
+    pub(crate) fn decode<S: Sample>(self, bytes: &[u8]) -> S {
+        match self {
+            Encoding::Unsigned8(bits) => S::from_int((bytes[0] as i32 - 128) >> (8 - bits), bits),
+            Encoding::Signed { bytes: width, bits } => {
+                // Place the sample in the most significant bytes so the shift sign-extends it and
+                // drops the padding below the valid bits.
+                let mut buf = [0; 4];
+                buf[4 - width..].copy_from_slice(bytes);
+                S::from_int(i32::from_le_bytes(buf) >> (32 - bits), bits)
+            }
+            Encoding::Float32 => {
+                S::from_float(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64)
+            }
+            Encoding::Float64 => {
+                let mut buf = [0; 8];
+                buf.copy_from_slice(bytes);
+                S::from_float(f64::from_le_bytes(buf))
+            }
+            Encoding::ALaw => S::from_int(alaw_to_linear(bytes[0]) as i32, 16),
+            Encoding::MuLaw => S::from_int(mulaw_to_linear(bytes[0]) as i32, 16),
+        }
+    }
+
+    fn encode<S: Sample>(self, sample: S, out: &mut Vec<u8>) {
+        match self {
+            Encoding::Unsigned8(bits) => {
+                out.push(((sample.to_int(bits) << (8 - bits)) + 128) as u8)
+            }
+            Encoding::Signed { bytes: width, bits } => {
+                let value = sample.to_int(bits) << (8 * width as u32 - bits);
+                out.extend_from_slice(&value.to_le_bytes()[..width]);
+            }
+            Encoding::Float32 => out.extend_from_slice(&(sample.to_float() as f32).to_le_bytes()),
+            Encoding::Float64 => out.extend_from_slice(&sample.to_float().to_le_bytes()),
+            Encoding::ALaw => out.push(linear_to_alaw(sample.to_int(16) as i16)),
+            Encoding::MuLaw => out.push(linear_to_mulaw(sample.to_int(16) as i16)),
+        }
+    }
+}
+
+/// Encodes `samples` in the layout described by `format`, appending them to `out`.
+pub(crate) fn encode<S: Sample>(
+    format: &FormatChunk,
+    samples: &[S],
+    out: &mut Vec<u8>,
+) -> Result<()> {
+    if let WaveFormat::ImaAdpcm | WaveFormat::MsAdpcm = format.effective_format() {
+        let samples: Vec<i16> = samples
+            .iter()
+            .map(|sample| sample.to_int(16) as i16)
+            .collect();
+        return match format.effective_format() {
+            WaveFormat::MsAdpcm => adpcm::encode_ms(format, &samples, out),
+            _ => adpcm::encode_ima(format, &samples, out),
+        };
+    }
+
+    let encoding = Encoding::from_format(format)?;
+    out.reserve(samples.len() * encoding.bytes_per_sample());
+    for &sample in samples {
+        encoding.encode(sample, out);
+    }
+    Ok(())
+}
+
+/// Returns the number of bytes a single sample of `format` occupies in the data chunk. Fails for
+/// block based formats.
+pub(crate) fn bytes_per_sample(format: &FormatChunk) -> Result<usize> {
+    Encoding::from_format(format).map(Encoding::bytes_per_sample)
+}
+
+/// Iterator over the decoded samples of a data chunk.
+///
+/// Samples of multi-channel audio are yielded interleaved, in the order they are stored.
+/// Created by [`Wave::samples`](crate::Wave::samples).
+#[derive(Debug, Clone)]
+pub struct Samples<'a, S> {
+    source: Source<'a>,
+    sample: PhantomData<S>,
+}
+
+#[derive(Debug, Clone)]
+enum Source<'a> {
+    /// Samples decoded one at a time from the data chunk.
+    Raw {
+        encoding: Encoding,
+        chunks: ChunksExact<'a, u8>,
+    },
+    /// 16-bit samples of a block based format, decoded up front.
+    Decoded { samples: Vec<i16>, index: usize },
+}
+
+impl<'a, S: Sample> Samples<'a, S> {
+    pub(crate) fn new(format: &FormatChunk, data: &'a [u8]) -> Result<Self> {
+        let source = match format.effective_format() {
+            WaveFormat::ImaAdpcm => Source::Decoded {
+                samples: adpcm::decode_ima(format, data)?,
+                index: 0,
+            },
+            WaveFormat::MsAdpcm => Source::Decoded {
+                samples: adpcm::decode_ms(format, data)?,
+                index: 0,
+            },
+            _ => {
+                let encoding = Encoding::from_format(format)?;
+                Source::Raw {
+                    encoding,
+                    chunks: data.chunks_exact(encoding.bytes_per_sample()),
+                }
+            }
+        };
+        Ok(Samples {
+            source,
+            sample: PhantomData,
+        })
+    }
+
+    /// Drops the samples past `len`, such as the padding at the end of the last block of a block
+    /// based format.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        if let Source::Decoded { samples, .. } = &mut self.source {
+            samples.truncate(len);
+        }
+    }
+}
+
+impl<'a, S: Sample> Iterator for Samples<'a, S> {
+    type Item = S;
+
+    fn next(&mut self) -> Option<S> {
+        match &mut self.source {
+            Source::Raw { encoding, chunks } => chunks.next().map(|bytes| encoding.decode(bytes)),
+            Source::Decoded { samples, index } => {
+                let sample = *samples.get(*index)?;
+                *index += 1;
+                Some(S::from_int(sample as i32, 16))
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.source {
+            Source::Raw { chunks, .. } => chunks.size_hint(),
+            Source::Decoded { samples, index } => {
+                let len = samples.len() - index;
+                (len, Some(len))
+            }
+        }
+    }
+}
+
+impl<'a, S: Sample> ExactSizeIterator for Samples<'a, S> {}
+
+/// Expands an ITU-T G.711 A-law byte to 16-bit linear PCM.
+fn alaw_to_linear(value: u8) -> i16 {
+    let value = value ^ 0x55;
+    let mut linear = ((value & 0x0F) as i16) << 4;
+    match (value & 0x70) >> 4 {
+        0 => linear += 8,
+        1 => linear += 0x108,
+        segment => linear = (linear + 0x108) << (segment - 1),
+    }
+    if value & 0x80 != 0 {
+        linear
+    } else {
+        -linear
+    }
+}
+
+/// Expands an ITU-T G.711 µ-law byte to 16-bit linear PCM.
+fn mulaw_to_linear(value: u8) -> i16 {
+    const BIAS: i16 = 0x84;
+    let value = !value;
+    let linear = ((((value & 0x0F) as i16) << 3) + BIAS) << ((value & 0x70) >> 4);
+    if value & 0x80 != 0 {
+        BIAS - linear
+    } else {
+        linear - BIAS
+    }
+}
+
+/// Index of the first G.711 segment whose upper bound is at least `value`.
+fn segment(value: i16, ends: &[i16; 8]) -> usize {
+    ends.iter().position(|&end| value <= end).unwrap_or(8)
+}
+
+/// Compresses 16-bit linear PCM to an ITU-T G.711 A-law byte.
+fn linear_to_alaw(value: i16) -> u8 {
+    const ENDS: [i16; 8] = [0x1F, 0x3F, 0x7F, 0xFF, 0x1FF, 0x3FF, 0x7FF, 0xFFF];
+    let value = value >> 3;
+    let (mask, value) = if value >= 0 {
+        (0xD5, value)
+    } else {
+        (0x55, -value - 1)
+    };
+    let segment = segment(value, &ENDS);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    let quantized = if segment < 2 {
+        value >> 1
+    } else {
+        value >> segment
+    };
+    (((segment as u8) << 4) | (quantized as u8 & 0x0F)) ^ mask
+}
+
+/// Compresses 16-bit linear PCM to an ITU-T G.711 µ-law byte.
+fn linear_to_mulaw(value: i16) -> u8 {
+    const ENDS: [i16; 8] = [0x3F, 0x7F, 0xFF, 0x1FF, 0x3FF, 0x7FF, 0xFFF, 0x1FFF];
+    const CLIP: i16 = 8159;
+    let value = value >> 2;
+    let (mask, value) = if value < 0 {
+        (0x7F, -value)
+    } else {
+        (0xFF, value)
+    };
+    let value = value.min(CLIP) + (0x84 >> 2);
+    let segment = segment(value, &ENDS);
+    if segment >= 8 {
+        return 0x7F ^ mask;
+    }
+    (((segment as u8) << 4) | ((value >> (segment + 1)) as u8 & 0x0F)) ^ mask
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChannelMask, ExtensibleFormat, SubFormat};
+
+    fn format(audio_format: WaveFormat, bits_per_sample: u16) -> FormatChunk {
+        FormatChunk {
+            size: 16,
+            audio_format,
+            num_channels: 1,
+            sample_rate: 8000,
+            byte_rate: 0,
+            block_align: 0,
+            bits_per_sample,
+            extension_size: None,
+            extensible: None,
+            extra: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn it_decodes_pcm_widths() -> Result<()> {
+        let eight = format(WaveFormat::Pcm, 8);
+        let samples: Samples<i16> = Samples::new(&eight, &[0x00, 0x80, 0xFF])?;
+        assert_eq!(samples.collect::<Vec<_>>(), [-32768, 0, 0x7F00]);
+
+        let twenty_four = format(WaveFormat::Pcm, 24);
+        let bytes = [0xFF, 0xFF, 0x7F, 0x00, 0x00, 0x80, 0x01, 0x00, 0x00];
+        let samples: Samples<i32> = Samples::new(&twenty_four, &bytes)?;
+        assert_eq!(samples.collect::<Vec<_>>(), [0x7FFFFF00, i32::MIN, 0x100]);
+
+        let samples: Samples<f32> = Samples::new(&twenty_four, &bytes[3..6])?;
+        assert_eq!(samples.collect::<Vec<_>>(), [-1.0]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_decodes_companded_formats() -> Result<()> {
+        let alaw = format(WaveFormat::Alaw, 8);
+        let samples: Samples<i16> = Samples::new(&alaw, &[0xD5, 0x55, 0xAA, 0x2A])?;
+        assert_eq!(samples.collect::<Vec<_>>(), [8, -8, 32256, -32256]);
+
+        let mulaw = format(WaveFormat::Mulaw, 8);
+        let samples: Samples<i16> = Samples::new(&mulaw, &[0xFF, 0x7F, 0x80, 0x00])?;
+        assert_eq!(samples.collect::<Vec<_>>(), [0, 0, 32124, -32124]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_round_trips_encoded_samples() -> Result<()> {
+        let input: [i16; 5] = [i16::MIN, -1000, 0, 1000, i16::MAX];
+        for (audio_format, bits_per_sample) in [
+            (WaveFormat::Pcm, 16),
+            (WaveFormat::Pcm, 24),
+            (WaveFormat::IeeeFloat, 32),
+        ] {
+            let format = format(audio_format, bits_per_sample);
+            let mut bytes = Vec::new();
+            encode(&format, &input, &mut bytes)?;
+            let output: Vec<i16> = Samples::new(&format, &bytes)?.collect();
+            assert_eq!(output, input);
+        }
+
+        let alaw = format(WaveFormat::Alaw, 8);
+        let mut bytes = Vec::new();
+        encode(&alaw, &[8i16, -8, 32256, -32256], &mut bytes)?;
+        assert_eq!(bytes, [0xD5, 0x55, 0xAA, 0x2A]);
+
+        let mulaw = format(WaveFormat::Mulaw, 8);
+        let mut bytes = Vec::new();
+        encode(&mulaw, &[0i16, 32124, -32124], &mut bytes)?;
+        assert_eq!(bytes, [0xFF, 0x80, 0x00]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn it_saturates_out_of_range_floats() -> Result<()> {
+        let pcm = format(WaveFormat::Pcm, 8);
+        let mut bytes = Vec::new();
+        encode(&pcm, &[-2.0f32, 0.0, 2.0], &mut bytes)?;
+        assert_eq!(bytes, [0x00, 0x80, 0xFF]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_decodes_valid_bits_in_containers() -> Result<()> {
+        // 12-bit samples are stored in the most significant bits of 16-bit containers.
+        let twelve = format(WaveFormat::Pcm, 12);
+        assert_eq!(twelve.container_bits(), 16);
+        let samples: Samples<i16> = Samples::new(&twelve, &[0xF0, 0x7F, 0x10, 0x00])?;
+        assert_eq!(samples.collect::<Vec<_>>(), [0x7FF0, 0x10]);
+
+        let mut twenty = format(WaveFormat::Extensible, 24);
+        twenty.extensible = Some(ExtensibleFormat {
+            valid_bits_per_sample: 20,
+            channel_mask: ChannelMask::MONO,
+            sub_format_guid: SubFormat::Pcm.guid(),
+        });
+        assert_eq!(twenty.valid_bits(), 20);
+        // The padding below the valid bits is ignored.
+        let bytes = [0x0F, 0x00, 0x80, 0xF0, 0xFF, 0x7F];
+        let samples: Samples<i32> = Samples::new(&twenty, &bytes)?;
+        assert_eq!(samples.collect::<Vec<_>>(), [i32::MIN, 0x7FFFF000]);
+
+        let mut bytes = Vec::new();
+        encode(&twenty, &[i32::MIN, 0x7FFFFFFF], &mut bytes)?;
+        assert_eq!(bytes, [0x00, 0x00, 0x80, 0xF0, 0xFF, 0x7F]);
+
+        twenty.extensible.as_mut().unwrap().valid_bits_per_sample = 28;
+        assert!(Samples::<i32>::new(&twenty, &bytes).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn it_rejects_unsupported_formats() {
+        let pcm64 = format(WaveFormat::Pcm, 64);
+        assert!(Samples::<f64>::new(&pcm64, &[]).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! The sampler and instrument chunks, which describe how samplers should play the audio.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use binrw::{binrw, BinReaderExt};
+
+/// Playback settings for samplers, such as the MIDI note the audio is recorded at and its loops.
+#[binrw]
+#[brw(magic = b"smpl")]
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SamplerChunk {
+    #[br(little)]
+    pub size: u32,
+    /// The MIDI manufacturer code of the sampler the chunk is intended for, or 0.
+    #[br(little)]
+    pub manufacturer: u32,
+    /// The product code of the sampler the chunk is intended for, or 0.
+    #[br(little)]
+    pub product: u32,
+    /// The duration of a sample frame in nanoseconds.
+    #[br(little)]
+    pub sample_period: u32,
+    /// The MIDI note that plays the audio at its original pitch.
+    #[br(little)]
+    pub midi_unity_note: u32,
+    /// The fraction of a semitone above the unity note, where `0x80000000` is half a semitone.
+    #[br(little)]
+    pub midi_pitch_fraction: u32,
+    /// The SMPTE frame rate of `smpte_offset`: 0, 24, 25, 29 (30 drop frame) or 30.
+    #[br(little)]
+    pub smpte_format: u32,
+    /// The SMPTE time of the first sample frame, packed as hours, minutes, seconds and frames.
+    #[br(little)]
+    pub smpte_offset: u32,
+    #[br(little)]
+    pub num_sample_loops: u32,
+    #[br(little)]
+    pub sampler_data_size: u32,
+    #[br(count = num_sample_loops)]
+    pub loops: Vec<SampleLoop>,
+    /// Data specific to the manufacturer and product.
+    #[br(count = sampler_data_size)]
+    pub sampler_data: Vec<u8>,
+    /// Bytes after the sampler data that the chunk size covers. Kept as is.
+    #[br(count = trailing_len(size, num_sample_loops, sampler_data_size))]
+    pub trailing: Vec<u8>,
+}
+
+/// The bytes a sampler chunk of `size` leaves after its loops and sampler data.
+fn trailing_len(size: u32, num_sample_loops: u32, sampler_data_size: u32) -> u64 {
+    let used = 36 + 24 * num_sample_loops as u64 + sampler_data_size as u64;
+    (size as u64).saturating_sub(used)
+}
+
+impl SamplerChunk {
+    /// Parses a sampler chunk, including its id and size. Returns `None` if the loops and
+    /// sampler data do not fit the chunk.
+    pub(crate) fn parse(chunk: &[u8]) -> Option<SamplerChunk> {
+        let u32_at = |offset: usize| -> Option<u32> {
+            Some(u32::from_le_bytes(
+                chunk.get(offset..offset + 4)?.try_into().ok()?,
+            ))
+        };
+        let used = 44 + 24 * u32_at(36)? as u64 + u32_at(40)? as u64;
+        if used > chunk.len() as u64 {
+            return None;
+        }
+        crate::parse_whole(chunk, |cursor| cursor.read_le())
+    }
+
+    /// Updates `size`, `num_sample_loops` and `sampler_data_size` to match `loops`,
+    /// `sampler_data` and `trailing`.
+    pub(crate) fn update_size(&mut self) {
+        self.num_sample_loops = self.loops.len() as u32;
+        self.sampler_data_size = self.sampler_data.len() as u32;
+        self.size =
+            36 + 24 * self.num_sample_loops + self.sampler_data_size + self.trailing.len() as u32;
+    }
+}
+
+/// A section of the audio that is repeated while a note is held.
+#[binrw]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SampleLoop {
+    /// Identifies the loop, and may refer to a cue point with the same id.
+    #[br(little)]
+    pub cue_point_id: u32,
+    #[br(little, map = LoopType::from_value)]
+    #[bw(map = LoopType::value)]
+    pub loop_type: LoopType,
+    /// The first sample frame of the loop.
+    #[br(little)]
+    pub start: u32,
+    /// The last sample frame of the loop, which is played.
+    #[br(little)]
+    pub end: u32,
+    /// The fraction of a sample frame past `end` the loop ends at.
+    #[br(little)]
+    pub fraction: u32,
+    /// The number of times the loop is played, where 0 is infinitely.
+    #[br(little)]
+    pub play_count: u32,
+}
+
+/// The direction a [`SampleLoop`] is played in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoopType {
+    Forward,
+    /// Forward, then backward.
+    Alternating,
+    Backward,
+    /// A type specific to a sampler.
+    Other(u32),
+}
+
+impl LoopType {
+    pub fn from_value(value: u32) -> LoopType {
+        match value {
+            0 => LoopType::Forward,
+            1 => LoopType::Alternating,
+            2 => LoopType::Backward,
+            value => LoopType::Other(value),
+        }
+    }
+
+    pub fn value(&self) -> u32 {
+        match self {
+            LoopType::Forward => 0,
+            LoopType::Alternating => 1,
+            LoopType::Backward => 2,
+            LoopType::Other(value) => *value,
+        }
+    }
+}
+
+/// The pitch, gain and key and velocity range a sampler should play the audio at.
+#[binrw]
+#[brw(magic = b"inst")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstrumentChunk {
+    #[br(little)]
+    pub size: u32,
+    /// The MIDI note that plays the audio at its original pitch.
+    pub unshifted_note: u8,
+    /// Pitch adjustment in cents, from -50 to 50.
+    pub fine_tune: i8,
+    /// Gain adjustment in dB.
+    pub gain: i8,
+    pub low_note: u8,
+    pub high_note: u8,
+    pub low_velocity: u8,
+    pub high_velocity: u8,
+}
+
+impl Default for InstrumentChunk {
+    /// Middle C over the full range of notes and velocities.
+    fn default() -> Self {
+        InstrumentChunk {
+            size: 7,
+            unshifted_note: 60,
+            fine_tune: 0,
+            gain: 0,
+            low_note: 0,
+            high_note: 127,
+            low_velocity: 1,
+            high_velocity: 127,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_maps_loop_types() {
+        for value in 0..4 {
+            assert_eq!(LoopType::from_value(value).value(), value);
+        }
+        assert_eq!(LoopType::from_value(1), LoopType::Alternating);
+        assert_eq!(LoopType::from_value(32), LoopType::Other(32));
+    }
+}
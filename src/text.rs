@@ -0,0 +1,60 @@
+//! Text of INFO tags, cue labels and fixed width fields, which is often Latin-1 rather than UTF-8.
+//!
+//! The bext chunk of EBU Tech 3285 and the cart chunk of AES46 share the same layout for text: a
+//! fixed width field of Latin-1 padded with NUL, and free text after the fixed fields that runs
+//! to the end of the chunk. Text read from a file always fits its field, so [`fits`] only fails
+//! for text set since. As bytes after the first NUL are not part of the text, both chunks keep
+//! the body they were read from, and write it back while their fields still match it.
+
+#[cfg(not(feature = "std"))]
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+#[cfg(feature = "std")]
+use std::borrow::Cow;
+
+/// The bytes before the first NUL.
+fn until_nul(data: &[u8]) -> &[u8] {
+    let end = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .unwrap_or(data.len());
+    &data[..end]
+}
+
+/// Each byte as the Latin-1 character of the same value.
+fn latin1(data: &[u8]) -> String {
+    data.iter().map(|&byte| byte as char).collect()
+}
+
+/// The text of a NUL terminated string, decoded as UTF-8 if it is valid and as Latin-1
+/// otherwise.
+pub(crate) fn utf8_or_latin1(data: &[u8]) -> Cow<'_, str> {
+    let data = until_nul(data);
+    match core::str::from_utf8(data) {
+        Ok(text) => Cow::Borrowed(text),
+        Err(_) => Cow::Owned(latin1(data)),
+    }
+}
+
+/// The text of a NUL padded field, one Latin-1 character per byte.
+pub(crate) fn field(data: &[u8]) -> String {
+    latin1(until_nul(data))
+}
+
+/// Whether `value` is Latin-1 and fits in `width` bytes.
+pub(crate) fn fits(value: &str, width: usize) -> bool {
+    value.chars().all(|c| u8::try_from(c).is_ok()) && value.chars().count() <= width
+}
+
+/// Appends `value` as Latin-1, writing characters outside of it as `?`.
+pub(crate) fn push_latin1(out: &mut Vec<u8>, value: &str) {
+    out.extend(value.chars().map(|c| u8::try_from(c).unwrap_or(b'?')));
+}
+
+/// Appends `value` as Latin-1 padded with NUL to `width` bytes, cutting off text that does not
+/// fit. See [`fits`].
+pub(crate) fn push_field(out: &mut Vec<u8>, value: &str, width: usize) {
+    let start = out.len();
+    push_latin1(out, value);
+    out.resize(start + width, 0);
+}
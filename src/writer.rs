@@ -0,0 +1,436 @@
+//! Incremental writing of WAV files whose length is not known up front.
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+use binrw::BinWriterExt;
+
+use crate::{adpcm, sample, stream_position};
+use crate::{
+    io, ChunkHeader, Container, Ds64Chunk, FactChunk, FormatChunk, Peak, PeakChunk, Result, Sample,
+    WaveFormat,
+};
+
+/// Writes a WAV file as samples become available, such as while recording.
+///
+/// The header is written up front with placeholder sizes, and the data chunk is written last.
+/// [`WaveWriter::finalize`] fills in the RIFF and data chunk sizes, the FACT sample count and
+/// the PEAK chunk. Dropping the writer does the same, but ignores any errors.
+///
+/// RIFF files reserve room for a ds64 chunk with a JUNK chunk, so that they can be turned into
+/// RF64 files when they grow past 4 GiB.
+///
+/// Created by [`WaveBuilder::writer`](crate::WaveBuilder::writer) or [`WaveWriter::new`].
+#[derive(Debug)]
+pub struct WaveWriter<W: io::Write + io::Seek> {
+    /// Only taken by `finalize`.
+    writer: Option<W>,
+    format: FormatChunk,
+    container: Container,
+    /// Offset of the RIFF chunk.
+    start: u64,
+    /// Offset of the ds64 chunk, or of the JUNK chunk reserving room for it.
+    ds64_offset: u64,
+    /// Offset of the FACT chunk, if the format needs one.
+    fact_offset: Option<u64>,
+    /// Offset of the PEAK chunk, along with the peaks so far.
+    peak: Option<(u64, PeakChunk)>,
+    /// Offset of the data chunk.
+    data_offset: u64,
+    data_len: u64,
+    frames: u64,
+    /// Sample frames per block of block based formats.
+    samples_per_block: Option<usize>,
+    /// Samples of block based formats waiting for a full block.
+    pending: Vec<i16>,
+    finished: bool,
+}
+
+impl<W: io::Write + io::Seek> WaveWriter<W> {
+    /// Writes the header of a new file with the given format. A FACT chunk is added for every
+    /// format other than PCM.
+    pub fn new(writer: W, format: FormatChunk) -> Result<Self> {
+        WaveWriter::start(writer, format, Container::Riff, None)
+    }
+
+    /// Writes the header of a new file, with a PEAK chunk dated `peak_timestamp` if given.
+    pub(crate) fn start(
+        mut writer: W,
+        mut format: FormatChunk,
+        container: Container,
+        peak_timestamp: Option<u32>,
+    ) -> Result<Self> {
+        let samples_per_block = match format.effective_format() {
+            WaveFormat::ImaAdpcm | WaveFormat::MsAdpcm => Some(adpcm::samples_per_block(&format)?),
+            _ => {
+                sample::bytes_per_sample(&format)?;
+                None
+            }
+        };
+        format.update_size();
+
+        let start = stream_position(&mut writer)?;
+        writer.write_le(&ChunkHeader {
+            id: container.id(),
+            size: 0,
+        })?;
+        writer.write_all(b"WAVE")?;
+        let ds64_offset = stream_position(&mut writer)?;
+        match container {
+            Container::Riff => {
+                writer.write_le(&ChunkHeader {
+                    id: *b"JUNK",
+                    size: 28,
+                })?;
+                writer.write_all(&[0; 28])?;
+            }
+            Container::Rf64 | Container::Bw64 => {
+                writer.write_le(&Ds64Chunk::new(0, 0, Vec::new()))?
+            }
+        }
+        writer.write_le(&format)?;
+
+        let fact_offset = if format.effective_format() != WaveFormat::Pcm {
+            let offset = stream_position(&mut writer)?;
+            writer.write_le(&FactChunk {
+                size: 4,
+                data: 0,
+                trailing: Vec::new(),
+            })?;
+            Some(offset)
+        } else {
+            None
+        };
+
+        let peak = match peak_timestamp {
+            Some(timestamp) => {
+                let num_channels = format.num_channels as usize;
+                let peak = PeakChunk {
+                    size: 8 + 8 * num_channels as u32,
+                    version: 1,
+                    timestamp,
+                    peaks: vec![
+                        Peak {
+                            value: 0.0,
+                            position: 0,
+                        };
+                        num_channels
+                    ],
+                };
+                let offset = stream_position(&mut writer)?;
+                writer.write_le(&peak)?;
+                Some((offset, peak))
+            }
+            None => None,
+        };
+
+        let data_offset = stream_position(&mut writer)?;
+        writer.write_le(&ChunkHeader {
+            id: *b"data",
+            size: 0,
+        })?;
+
+        Ok(WaveWriter {
+            writer: Some(writer),
+            format,
+            container,
+            start,
+            ds64_offset,
+            fact_offset,
+            peak,
+            data_offset,
+            data_len: 0,
+            frames: 0,
+            samples_per_block,
+            pending: Vec::new(),
+            finished: false,
+        })
+    }
+
+    pub fn format(&self) -> &FormatChunk {
+        &self.format
+    }
+
+    /// Number of sample frames written so far.
+    pub fn num_frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Encodes and appends `samples`, interleaved by channel, to the data chunk. Samples of block
+    /// based formats are held back until they fill a block, or the writer is finalized.
+    pub fn write_samples<S: Sample>(&mut self, samples: &[S]) -> Result<()> {
+        let num_channels = self.format.num_channels as usize;
+        if !samples.len().is_multiple_of(num_channels) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Samples must contain a whole number of frames.",
+            )
+            .into());
+        }
+
+        let mut data = Vec::new();
+        match self.samples_per_block {
+            Some(samples_per_block) => {
+                let block = samples_per_block * num_channels;
+                self.pending
+                    .extend(samples.iter().map(|sample| sample.to_int(16) as i16));
+                let full = self.pending.len() / block * block;
+                sample::encode(&self.format, &self.pending[..full], &mut data)?;
+                self.pending.drain(..full);
+            }
+            None => sample::encode(&self.format, samples, &mut data)?,
+        }
+        self.write_data(&data)?;
+
+        if let Some((_, peak)) = &mut self.peak {
+            for (index, sample) in samples.iter().enumerate() {
+                let value = sample.to_float().abs() as f32;
+                let channel = &mut peak.peaks[index % num_channels];
+                if value > channel.value {
+                    channel.value = value;
+                    channel.position = (self.frames + (index / num_channels) as u64) as u32;
+                }
+            }
+        }
+        self.frames += (samples.len() / num_channels) as u64;
+        Ok(())
+    }
+
+    fn write_data(&mut self, data: &[u8]) -> Result<()> {
+        self.writer.as_mut().unwrap().write_all(data)?;
+        self.data_len += data.len() as u64;
+        Ok(())
+    }
+
+    /// Writes any held back samples, then fills in the sizes, FACT sample count and PEAK chunk,
+    /// switching to RF64 if the file has grown past 4 GiB.
+    fn finish(&mut self) -> Result<()> {
+        self.finished = true;
+        if !self.pending.is_empty() {
+            let mut data = Vec::new();
+            sample::encode(&self.format, &self.pending, &mut data)?;
+            self.pending.clear();
+            self.write_data(&data)?;
+        }
+
+        let writer = self.writer.as_mut().unwrap();
+        if self.data_len % 2 == 1 {
+            writer.write_all(&[0])?;
+        }
+        let end = stream_position(writer)?;
+        let riff_size = end - self.start - 8;
+        let container = match self.container {
+            Container::Riff if riff_size > u32::MAX as u64 => Container::Rf64,
+            container => container,
+        };
+
+        writer.seek(io::SeekFrom::Start(self.data_offset + 4))?;
+        let data_size = if container.has_ds64() {
+            u32::MAX
+        } else {
+            self.data_len as u32
+        };
+        writer.write_le(&data_size)?;
+        if let Some(offset) = self.fact_offset {
+            writer.seek(io::SeekFrom::Start(offset + 8))?;
+            writer.write_le(&u32::try_from(self.frames).unwrap_or(u32::MAX))?;
+        }
+        if let Some((offset, peak)) = &self.peak {
+            writer.seek(io::SeekFrom::Start(*offset))?;
+            writer.write_le(peak)?;
+        }
+        writer.seek(io::SeekFrom::Start(self.start))?;
+        if container.has_ds64() {
+            writer.write_le(&ChunkHeader {
+                id: container.id(),
+                size: u32::MAX,
+            })?;
+            let mut ds64 = Ds64Chunk::new(self.data_len, self.frames, Vec::new());
+            ds64.riff_size = riff_size;
+            writer.seek(io::SeekFrom::Start(self.ds64_offset))?;
+            writer.write_le(&ds64)?;
+        } else {
+            writer.write_le(&ChunkHeader {
+                id: Container::Riff.id(),
+                size: riff_size as u32,
+            })?;
+        }
+
+        writer.seek(io::SeekFrom::Start(end))?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Completes the file, returning the underlying writer positioned at its end.
+    pub fn finalize(mut self) -> Result<W> {
+        self.finish()?;
+        Ok(self.writer.take().unwrap())
+    }
+}
+
+impl<W: io::Write + io::Seek> Drop for WaveWriter<W> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.finish();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::{Wave, WaveBuilder, WaveReader};
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+    use std::vec::Vec;
+
+    /// A file that keeps its first bytes, and only the length of the rest, which reads as zeros.
+    #[derive(Default)]
+    struct Sparse {
+        head: Vec<u8>,
+        position: u64,
+        len: u64,
+    }
+
+    impl Sparse {
+        const HEAD: usize = 256;
+    }
+
+    impl Write for Sparse {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            for (index, &byte) in buf.iter().enumerate() {
+                let position = self.position as usize + index;
+                if position < Sparse::HEAD {
+                    if self.head.len() <= position {
+                        self.head.resize(position + 1, 0);
+                    }
+                    self.head[position] = byte;
+                }
+            }
+            self.position += buf.len() as u64;
+            self.len = self.len.max(self.position);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Read for Sparse {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min((self.len - self.position) as usize);
+            for (index, byte) in buf[..len].iter_mut().enumerate() {
+                let position = self.position as usize + index;
+                *byte = self.head.get(position).copied().unwrap_or(0);
+            }
+            self.position += len as u64;
+            Ok(len)
+        }
+    }
+
+    impl Seek for Sparse {
+        fn seek(&mut self, position: SeekFrom) -> io::Result<u64> {
+            self.position = match position {
+                SeekFrom::Start(offset) => offset,
+                SeekFrom::End(offset) => (self.len as i64 + offset) as u64,
+                SeekFrom::Current(offset) => (self.position as i64 + offset) as u64,
+            };
+            Ok(self.position)
+        }
+    }
+
+    #[test]
+    fn it_writes_what_the_builder_builds() -> Result<()> {
+        let samples: Vec<i16> = (0..3000).map(|index| index * 7 - 9000).collect();
+        for (audio_format, bits_per_sample) in [(WaveFormat::Pcm, 16), (WaveFormat::IeeeFloat, 32)]
+        {
+            let builder =
+                WaveBuilder::new(8000, 2, audio_format, bits_per_sample).peak(1_600_000_000);
+            let mut expected = Cursor::new(Vec::new());
+            builder.clone().build(&samples)?.write(&mut expected)?;
+
+            let mut writer = builder.writer(Cursor::new(Vec::new()))?;
+            for chunk in samples.chunks(1000) {
+                writer.write_samples(chunk)?;
+            }
+            assert_eq!(writer.num_frames(), 1500);
+            let file = writer.finalize()?.into_inner();
+
+            let expected = Wave::from_reader(Cursor::new(expected.into_inner()))?;
+            let wave = Wave::from_reader(Cursor::new(file))?;
+            assert_eq!(wave.unknown[0].id, *b"JUNK");
+            assert_eq!(wave.format, expected.format);
+            assert_eq!(wave.fact, expected.fact);
+            assert_eq!(wave.peak, expected.peak);
+            assert_eq!(wave.data, expected.data);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn it_finalizes_on_drop() -> Result<()> {
+        let mut file = Cursor::new(Vec::new());
+        {
+            let builder = WaveBuilder::new(8000, 1, WaveFormat::Pcm, 8);
+            let mut writer = builder.writer(&mut file)?;
+            writer.write_samples(&[0.5f32, -0.5, 0.25])?;
+            writer.write_samples::<i16>(&[])?;
+        }
+        let bytes = file.into_inner();
+        // The odd sized data chunk is followed by a pad byte.
+        assert_eq!(bytes.len(), 44 + 36 + 3 + 1);
+
+        let wave = Wave::from_reader(Cursor::new(bytes))?;
+        assert_eq!(wave.data.data, [0xC0, 0x40, 0xA0]);
+        Ok(())
+    }
+
+    #[test]
+    fn it_holds_back_partial_blocks() -> Result<()> {
+        let samples: Vec<i16> = (0..1200).map(|index| (index % 50) * 200 - 5000).collect();
+        let builder = WaveBuilder::new(8000, 1, WaveFormat::ImaAdpcm, 4);
+        let mut writer = builder.writer(Cursor::new(Vec::new()))?;
+        for chunk in samples.chunks(100) {
+            writer.write_samples(chunk)?;
+        }
+        let file = writer.finalize()?.into_inner();
+
+        let wave = Wave::from_reader(Cursor::new(file))?;
+        assert_eq!(wave.fact.as_ref().map(|fact| fact.data), Some(1200));
+        assert_eq!(wave.data.size, 2 * 256 + 4 + 24 * 4);
+        assert_eq!(wave.samples::<i16>()?.len(), 1200);
+        Ok(())
+    }
+
+    #[test]
+    fn it_switches_to_rf64_past_4_gib() -> Result<()> {
+        let mut writer =
+            WaveBuilder::new(48000, 2, WaveFormat::Pcm, 24).writer(Sparse::default())?;
+        writer.write_samples(&[0i32; 6])?;
+        // Skip ahead as if 5 GiB of silence had been written.
+        let skipped = 5 << 30;
+        let file = writer.writer.as_mut().unwrap();
+        file.position += skipped;
+        file.len = file.position;
+        writer.data_len += skipped;
+        writer.frames += skipped / 6;
+        let mut file = writer.finalize()?;
+
+        assert_eq!(
+            file.head[..8],
+            [b'R', b'F', b'6', b'4', 0xFF, 0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(file.head[12..16], *b"ds64");
+        file.seek(SeekFrom::Start(0))?;
+        let reader = WaveReader::new(file)?;
+        let wave = reader.wave();
+        assert_eq!(wave.container, Container::Rf64);
+        let ds64 = wave.ds64.as_ref().unwrap();
+        assert_eq!(ds64.riff_size, 12 + 36 + 24 + 8 + 18 + skipped - 8);
+        assert_eq!(ds64.sample_count, 3 + skipped / 6);
+        assert_eq!(reader.data_len(), 18 + skipped);
+        Ok(())
+    }
+}